@@ -0,0 +1,95 @@
+//! Pack the user apps into an easy-fs image.
+//!
+//! easy-fs-fuse -s <app sources> -t <app elfs> -o <image>
+
+use easy_fs::{BlockDevice, DiskInodeType, EasyFileSystem, EfsError, BLOCK_SZ};
+use std::env;
+use std::fs::{read_dir, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::process::exit;
+use std::sync::{Arc, Mutex};
+
+// 16 MiB
+const TOTAL_BLOCKS: u32 = 32 * 1024;
+const INODE_BITMAP_BLOCKS: u32 = 1;
+
+struct BlockFile(Mutex<File>);
+
+impl BlockDevice for BlockFile {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) -> Result<(), EfsError> {
+        let mut file = self.0.lock().unwrap();
+        file.seek(SeekFrom::Start((block_id * BLOCK_SZ) as u64))
+            .and_then(|_| file.read_exact(buf))
+            .map_err(|_| EfsError::Io)
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) -> Result<(), EfsError> {
+        let mut file = self.0.lock().unwrap();
+        file.seek(SeekFrom::Start((block_id * BLOCK_SZ) as u64))
+            .and_then(|_| file.write_all(buf))
+            .map_err(|_| EfsError::Io)
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("usage: easy-fs-fuse -s <app sources> -t <app elfs> -o <image>");
+    exit(1);
+}
+
+fn main() {
+    let mut src_path = None;
+    let mut target_path = None;
+    let mut image_path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "-s" => src_path = Some(value),
+            "-t" => target_path = Some(value),
+            "-o" => image_path = Some(value),
+            _ => usage(),
+        }
+    }
+    let (src_path, target_path, image_path) = match (src_path, target_path, image_path) {
+        (Some(s), Some(t), Some(o)) => (s, t, o),
+        _ => usage(),
+    };
+
+    let image = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&image_path)
+        .expect("Cannot create the image");
+    image
+        .set_len(TOTAL_BLOCKS as u64 * BLOCK_SZ as u64)
+        .unwrap();
+    let device: Arc<dyn BlockDevice> = Arc::new(BlockFile(Mutex::new(image)));
+    let efs = EasyFileSystem::create(device, TOTAL_BLOCKS, INODE_BITMAP_BLOCKS)
+        .expect("Cannot format the image");
+    let root = EasyFileSystem::root_inode(&efs);
+
+    let mut apps: Vec<String> = read_dir(&src_path)
+        .expect("Cannot read the app sources")
+        .map(|dir_entry| {
+            let mut name = dir_entry.unwrap().file_name().into_string().unwrap();
+            name.drain(name.find('.').unwrap()..name.len());
+            name
+        })
+        .collect();
+    apps.sort();
+    for app in apps.iter() {
+        let mut elf = File::open(format!("{}/{}", target_path, app))
+            .unwrap_or_else(|_| panic!("Cannot open the elf of {}", app));
+        let mut data = Vec::new();
+        elf.read_to_end(&mut data).unwrap();
+        let inode = root
+            .create(app, DiskInodeType::File)
+            .expect("Cannot write the image")
+            .unwrap_or_else(|| panic!("Cannot create {}", app));
+        let written = inode.write_at(0, &data).expect("Cannot write the image");
+        assert_eq!(written, data.len(), "The image is full");
+        println!("{}: {} bytes", app, data.len());
+    }
+}
@@ -0,0 +1,71 @@
+use super::{
+    block_dev::{modify_at, BlockDevice},
+    EfsError, BLOCK_SZ,
+};
+use alloc::sync::Arc;
+
+type BitmapBlock = [u64; 64];
+
+const BLOCK_BITS: usize = BLOCK_SZ * 8;
+
+pub struct Bitmap {
+    start_block_id: usize,
+    blocks: usize,
+}
+
+impl Bitmap {
+    pub fn new(start_block_id: usize, blocks: usize) -> Self {
+        Self {
+            start_block_id,
+            blocks,
+        }
+    }
+
+    // Set the first clear bit and return its index, None if every bit is set
+    pub fn alloc(&self, device: &Arc<dyn BlockDevice>) -> Result<Option<usize>, EfsError> {
+        for block in 0..self.blocks {
+            let pos = modify_at(
+                device,
+                self.start_block_id + block,
+                0,
+                |bitmap: &mut BitmapBlock| {
+                    let (pos, bits) = bitmap
+                        .iter_mut()
+                        .enumerate()
+                        .find(|(_, _bits)| **_bits != u64::MAX)?;
+                    let inner = bits.trailing_ones() as usize;
+                    *bits |= 1 << inner;
+                    Some(pos * 64 + inner)
+                },
+            )?;
+            if let Some(pos) = pos {
+                return Ok(Some(block * BLOCK_BITS + pos));
+            }
+        }
+        Ok(None)
+    }
+
+    // Freeing a clear bit means the disk is inconsistent
+    pub fn dealloc(&self, device: &Arc<dyn BlockDevice>, bit: usize) -> Result<(), EfsError> {
+        let (block, pos, inner) = (bit / BLOCK_BITS, bit % BLOCK_BITS / 64, bit % 64);
+        if block >= self.blocks {
+            return Err(EfsError::Corrupted);
+        }
+        modify_at(
+            device,
+            self.start_block_id + block,
+            0,
+            |bitmap: &mut BitmapBlock| {
+                if bitmap[pos] & (1 << inner) == 0 {
+                    return Err(EfsError::Corrupted);
+                }
+                bitmap[pos] &= !(1 << inner);
+                Ok(())
+            },
+        )?
+    }
+
+    pub fn maximum(&self) -> usize {
+        self.blocks * BLOCK_BITS
+    }
+}
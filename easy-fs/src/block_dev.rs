@@ -0,0 +1,44 @@
+use super::{EfsError, BLOCK_SZ};
+use alloc::sync::Arc;
+use core::any::Any;
+
+pub trait BlockDevice: Send + Sync + Any {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) -> Result<(), EfsError>;
+    fn write_block(&self, block_id: usize, buf: &[u8]) -> Result<(), EfsError>;
+}
+
+// Aligned so that the on-disk structures can be viewed in place
+#[repr(C, align(8))]
+struct Block([u8; BLOCK_SZ]);
+
+// Run f on the T stored at offset of the block
+pub fn read_at<T, V>(
+    device: &Arc<dyn BlockDevice>,
+    block_id: usize,
+    offset: usize,
+    f: impl FnOnce(&T) -> V,
+) -> Result<V, EfsError> {
+    assert!(offset + core::mem::size_of::<T>() <= BLOCK_SZ);
+    let mut block = Block([0; BLOCK_SZ]);
+    device.read_block(block_id, &mut block.0)?;
+    Ok(f(unsafe { &*(block.0.as_ptr().add(offset) as *const T) }))
+}
+
+// Run f on the T stored at offset of the block and write the block back
+pub fn modify_at<T, V>(
+    device: &Arc<dyn BlockDevice>,
+    block_id: usize,
+    offset: usize,
+    f: impl FnOnce(&mut T) -> V,
+) -> Result<V, EfsError> {
+    assert!(offset + core::mem::size_of::<T>() <= BLOCK_SZ);
+    let mut block = Block([0; BLOCK_SZ]);
+    device.read_block(block_id, &mut block.0)?;
+    let r = f(unsafe { &mut *(block.0.as_mut_ptr().add(offset) as *mut T) });
+    device.write_block(block_id, &block.0)?;
+    Ok(r)
+}
+
+pub fn zero_block(device: &Arc<dyn BlockDevice>, block_id: usize) -> Result<(), EfsError> {
+    device.write_block(block_id, &[0u8; BLOCK_SZ])
+}
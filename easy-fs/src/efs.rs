@@ -0,0 +1,148 @@
+use super::{
+    bitmap::Bitmap,
+    block_dev::{modify_at, read_at, zero_block, BlockDevice},
+    layout::{DiskInode, DiskInodeType, SuperBlock},
+    vfs::Inode,
+    EfsError, BLOCK_SZ,
+};
+use alloc::sync::Arc;
+use spin::Mutex;
+
+pub struct EasyFileSystem {
+    pub block_device: Arc<dyn BlockDevice>,
+    inode_bitmap: Bitmap,
+    data_bitmap: Bitmap,
+    inode_area_start_block: u32,
+    inode_area_blocks: u32,
+    data_area_start_block: u32,
+    data_area_blocks: u32,
+}
+
+const INODES_PER_BLOCK: usize = BLOCK_SZ / core::mem::size_of::<DiskInode>();
+
+impl EasyFileSystem {
+    // Format the device, the root directory is inode 0
+    pub fn create(
+        block_device: Arc<dyn BlockDevice>,
+        total_blocks: u32,
+        inode_bitmap_blocks: u32,
+    ) -> Result<Arc<Mutex<Self>>, EfsError> {
+        let inode_bitmap = Bitmap::new(1, inode_bitmap_blocks as usize);
+        let inode_num = inode_bitmap.maximum();
+        let inode_area_blocks = ((inode_num + INODES_PER_BLOCK - 1) / INODES_PER_BLOCK) as u32;
+        let inode_total_blocks = inode_bitmap_blocks + inode_area_blocks;
+        let data_total_blocks = total_blocks - 1 - inode_total_blocks;
+        // A bitmap block covers itself and BLOCK_SZ * 8 data blocks
+        let data_bitmap_blocks = (data_total_blocks + BLOCK_SZ as u32 * 8) / (BLOCK_SZ as u32 * 8 + 1);
+        let data_area_blocks = data_total_blocks - data_bitmap_blocks;
+        let mut efs = Self {
+            block_device: block_device.clone(),
+            inode_bitmap,
+            data_bitmap: Bitmap::new(
+                (1 + inode_total_blocks) as usize,
+                data_bitmap_blocks as usize,
+            ),
+            inode_area_start_block: 1 + inode_bitmap_blocks,
+            inode_area_blocks,
+            data_area_start_block: 1 + inode_total_blocks + data_bitmap_blocks,
+            data_area_blocks,
+        };
+        for _i in 0..total_blocks {
+            zero_block(&block_device, _i as usize)?;
+        }
+        modify_at(&block_device, 0, 0, |_sb: &mut SuperBlock| {
+            _sb.initialize(
+                total_blocks,
+                inode_bitmap_blocks,
+                inode_area_blocks,
+                data_bitmap_blocks,
+                data_area_blocks,
+            );
+        })?;
+        assert_eq!(efs.alloc_inode()?, Some(0));
+        let (block_id, offset) = efs.get_disk_inode_pos(0)?;
+        modify_at(&block_device, block_id, offset, |_di: &mut DiskInode| {
+            _di.initialize(DiskInodeType::Directory);
+        })?;
+        Ok(Arc::new(Mutex::new(efs)))
+    }
+
+    // Return Corrupted if the device holds no valid easy-fs
+    pub fn open(block_device: Arc<dyn BlockDevice>) -> Result<Arc<Mutex<Self>>, EfsError> {
+        read_at(&block_device, 0, 0, |_sb: &SuperBlock| {
+            if !_sb.is_valid() {
+                return Err(EfsError::Corrupted);
+            }
+            let inode_total_blocks = _sb.inode_bitmap_blocks + _sb.inode_area_blocks;
+            let efs = Self {
+                block_device: block_device.clone(),
+                inode_bitmap: Bitmap::new(1, _sb.inode_bitmap_blocks as usize),
+                data_bitmap: Bitmap::new(
+                    (1 + inode_total_blocks) as usize,
+                    _sb.data_bitmap_blocks as usize,
+                ),
+                inode_area_start_block: 1 + _sb.inode_bitmap_blocks,
+                inode_area_blocks: _sb.inode_area_blocks,
+                data_area_start_block: 1 + inode_total_blocks + _sb.data_bitmap_blocks,
+                data_area_blocks: _sb.data_area_blocks,
+            };
+            Ok(Arc::new(Mutex::new(efs)))
+        })?
+    }
+
+    pub fn root_inode(efs: &Arc<Mutex<Self>>) -> Inode {
+        // Inode 0 is in the inode area of any valid easy-fs
+        let (block_id, offset) = efs.lock().get_disk_inode_pos(0).unwrap();
+        Inode::new(0, block_id, offset, efs.clone())
+    }
+
+    // The block and the offset in it of the disk inode.
+    // The id may come from the disk, it must lie in the inode area.
+    pub fn get_disk_inode_pos(&self, inode_id: u32) -> Result<(usize, usize), EfsError> {
+        let inode_id = inode_id as usize;
+        if inode_id >= self.inode_area_blocks as usize * INODES_PER_BLOCK {
+            return Err(EfsError::Corrupted);
+        }
+        Ok((
+            self.inode_area_start_block as usize + inode_id / INODES_PER_BLOCK,
+            inode_id % INODES_PER_BLOCK * core::mem::size_of::<DiskInode>(),
+        ))
+    }
+
+    pub fn alloc_inode(&mut self) -> Result<Option<u32>, EfsError> {
+        let id = self.inode_bitmap.alloc(&self.block_device)?;
+        Ok(id.map(|_id| _id as u32))
+    }
+
+    // Return a zeroed data block or None if the disk is full
+    pub fn alloc_data(&mut self) -> Result<Option<u32>, EfsError> {
+        let id = match self.data_bitmap.alloc(&self.block_device)? {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+        if id >= self.data_area_blocks as usize {
+            self.data_bitmap.dealloc(&self.block_device, id)?;
+            return Ok(None);
+        }
+        let block_id = self.data_area_start_block + id as u32;
+        zero_block(&self.block_device, block_id as usize)?;
+        Ok(Some(block_id))
+    }
+
+    pub fn dealloc_data(&mut self, block_id: u32) -> Result<(), EfsError> {
+        let block_id = self.check_data_block(block_id)?;
+        self.data_bitmap.dealloc(
+            &self.block_device,
+            (block_id - self.data_area_start_block) as usize,
+        )
+    }
+
+    // A block id read from the disk must lie in the data area
+    pub fn check_data_block(&self, block_id: u32) -> Result<u32, EfsError> {
+        let start = self.data_area_start_block;
+        if block_id < start || block_id - start >= self.data_area_blocks {
+            return Err(EfsError::Corrupted);
+        }
+        Ok(block_id)
+    }
+}
@@ -0,0 +1,315 @@
+use super::{
+    block_dev::{modify_at, read_at},
+    efs::EasyFileSystem,
+    EfsError, BLOCK_SZ,
+};
+use alloc::vec::Vec;
+
+const EFS_MAGIC: u32 = 0x3b80_0001;
+const INODE_DIRECT_COUNT: usize = 28;
+const INODE_INDIRECT_COUNT: usize = BLOCK_SZ / 4;
+const DIRECT_BOUND: usize = INODE_DIRECT_COUNT;
+const INDIRECT1_BOUND: usize = DIRECT_BOUND + INODE_INDIRECT_COUNT;
+const INDIRECT2_BOUND: usize = INDIRECT1_BOUND + INODE_INDIRECT_COUNT * INODE_INDIRECT_COUNT;
+pub const NAME_LENGTH_LIMIT: usize = 27;
+pub const DIRENT_SZ: usize = 32;
+
+type IndirectBlock = [u32; INODE_INDIRECT_COUNT];
+type DataBlock = [u8; BLOCK_SZ];
+
+#[repr(C)]
+pub struct SuperBlock {
+    magic: u32,
+    pub total_blocks: u32,
+    pub inode_bitmap_blocks: u32,
+    pub inode_area_blocks: u32,
+    pub data_bitmap_blocks: u32,
+    pub data_area_blocks: u32,
+}
+
+impl SuperBlock {
+    pub fn initialize(
+        &mut self,
+        total_blocks: u32,
+        inode_bitmap_blocks: u32,
+        inode_area_blocks: u32,
+        data_bitmap_blocks: u32,
+        data_area_blocks: u32,
+    ) {
+        *self = Self {
+            magic: EFS_MAGIC,
+            total_blocks,
+            inode_bitmap_blocks,
+            inode_area_blocks,
+            data_bitmap_blocks,
+            data_area_blocks,
+        }
+    }
+    // The areas must fit in the disk and hold the root inode
+    pub fn is_valid(&self) -> bool {
+        let used = [
+            self.inode_bitmap_blocks,
+            self.inode_area_blocks,
+            self.data_bitmap_blocks,
+            self.data_area_blocks,
+        ]
+        .iter()
+        .try_fold(1u32, |_sum, _blocks| _sum.checked_add(*_blocks));
+        self.magic == EFS_MAGIC
+            && self.inode_area_blocks > 0
+            && used.map_or(false, |_used| _used <= self.total_blocks)
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum DiskInodeType {
+    File,
+    Directory,
+}
+
+// 128 bytes, four inodes in a block.
+// The data blocks are found through 28 direct entries,
+// an indirect block and a doubly indirect block.
+#[repr(C)]
+pub struct DiskInode {
+    pub size: u32,
+    direct: [u32; INODE_DIRECT_COUNT],
+    indirect1: u32,
+    indirect2: u32,
+    // 0 for a file, 1 for a directory
+    _type: u32,
+}
+
+impl DiskInode {
+    pub fn initialize(&mut self, _type: DiskInodeType) {
+        self.size = 0;
+        self.direct = [0; INODE_DIRECT_COUNT];
+        self.indirect1 = 0;
+        self.indirect2 = 0;
+        self._type = match _type {
+            DiskInodeType::File => 0,
+            DiskInodeType::Directory => 1,
+        };
+    }
+    pub fn get_type(&self) -> DiskInodeType {
+        if self._type == 1 {
+            DiskInodeType::Directory
+        } else {
+            DiskInodeType::File
+        }
+    }
+    pub fn is_dir(&self) -> bool {
+        self.get_type() == DiskInodeType::Directory
+    }
+
+    fn data_blocks(size: u32) -> usize {
+        (size as usize + BLOCK_SZ - 1) / BLOCK_SZ
+    }
+    // The data blocks and the indirect blocks
+    fn total_blocks(size: u32) -> usize {
+        let data = Self::data_blocks(size);
+        let mut total = data;
+        if data > DIRECT_BOUND {
+            total += 1;
+        }
+        if data > INDIRECT1_BOUND {
+            total += 1 + (data - INDIRECT1_BOUND + INODE_INDIRECT_COUNT - 1) / INODE_INDIRECT_COUNT;
+        }
+        total
+    }
+    // The number of blocks to allocate before growing to new_size
+    pub fn blocks_num_needed(&self, new_size: u32) -> usize {
+        assert!(new_size >= self.size);
+        Self::total_blocks(new_size) - Self::total_blocks(self.size)
+    }
+    pub fn max_size() -> usize {
+        INDIRECT2_BOUND * BLOCK_SZ
+    }
+
+    // The block id of the inner_id-th data block.
+    // Every id on the way is checked against the data area.
+    fn get_block_id(&self, inner_id: usize, fs: &EasyFileSystem) -> Result<u32, EfsError> {
+        let device = &fs.block_device;
+        let block_id = if inner_id < DIRECT_BOUND {
+            self.direct[inner_id]
+        } else if inner_id < INDIRECT1_BOUND {
+            let indirect1 = fs.check_data_block(self.indirect1)?;
+            read_at(device, indirect1 as usize, 0, |_b: &IndirectBlock| {
+                _b[inner_id - DIRECT_BOUND]
+            })?
+        } else if inner_id < INDIRECT2_BOUND {
+            let last = inner_id - INDIRECT1_BOUND;
+            let indirect2 = fs.check_data_block(self.indirect2)?;
+            let indirect1 = read_at(device, indirect2 as usize, 0, |_b: &IndirectBlock| {
+                _b[last / INODE_INDIRECT_COUNT]
+            })?;
+            let indirect1 = fs.check_data_block(indirect1)?;
+            read_at(device, indirect1 as usize, 0, |_b: &IndirectBlock| {
+                _b[last % INODE_INDIRECT_COUNT]
+            })?
+        } else {
+            // The size on the disk is beyond the limit
+            return Err(EfsError::Corrupted);
+        };
+        fs.check_data_block(block_id)
+    }
+
+    // Grow to new_size with the blocks counted by blocks_num_needed
+    pub fn increase_size(
+        &mut self,
+        new_size: u32,
+        new_blocks: Vec<u32>,
+        fs: &EasyFileSystem,
+    ) -> Result<(), EfsError> {
+        let device = &fs.block_device;
+        let mut blocks = new_blocks.into_iter();
+        let old_data = Self::data_blocks(self.size);
+        for inner_id in old_data..Self::data_blocks(new_size) {
+            if inner_id < DIRECT_BOUND {
+                self.direct[inner_id] = blocks.next().unwrap();
+            } else if inner_id < INDIRECT1_BOUND {
+                if inner_id == DIRECT_BOUND {
+                    self.indirect1 = blocks.next().unwrap();
+                }
+                let indirect1 = fs.check_data_block(self.indirect1)?;
+                let block = blocks.next().unwrap();
+                modify_at(device, indirect1 as usize, 0, |_b: &mut IndirectBlock| {
+                    _b[inner_id - DIRECT_BOUND] = block;
+                })?;
+            } else {
+                let last = inner_id - INDIRECT1_BOUND;
+                if last == 0 {
+                    self.indirect2 = blocks.next().unwrap();
+                }
+                let indirect2 = fs.check_data_block(self.indirect2)?;
+                let (pos, inner) = (last / INODE_INDIRECT_COUNT, last % INODE_INDIRECT_COUNT);
+                if inner == 0 {
+                    let indirect1 = blocks.next().unwrap();
+                    modify_at(device, indirect2 as usize, 0, |_b: &mut IndirectBlock| {
+                        _b[pos] = indirect1;
+                    })?;
+                }
+                let indirect1 = read_at(device, indirect2 as usize, 0, |_b: &IndirectBlock| {
+                    _b[pos]
+                })?;
+                let indirect1 = fs.check_data_block(indirect1)?;
+                let block = blocks.next().unwrap();
+                modify_at(device, indirect1 as usize, 0, |_b: &mut IndirectBlock| {
+                    _b[inner] = block;
+                })?;
+            }
+        }
+        // The size moves last, a failed grow leaves the file as it was
+        self.size = new_size;
+        assert!(blocks.next().is_none());
+        Ok(())
+    }
+
+    // Shrink to 0 and return the blocks to free.
+    // The inode is kept as it is if a block can't be read.
+    pub fn clear_size(&mut self, fs: &EasyFileSystem) -> Result<Vec<u32>, EfsError> {
+        let data = Self::data_blocks(self.size);
+        if data > INDIRECT2_BOUND {
+            return Err(EfsError::Corrupted);
+        }
+        let mut blocks = Vec::new();
+        for _i in 0..data {
+            blocks.push(self.get_block_id(_i, fs)?);
+        }
+        if data > DIRECT_BOUND {
+            blocks.push(self.indirect1);
+        }
+        if data > INDIRECT1_BOUND {
+            blocks.push(self.indirect2);
+            let count = (data - INDIRECT1_BOUND + INODE_INDIRECT_COUNT - 1) / INODE_INDIRECT_COUNT;
+            read_at(&fs.block_device, self.indirect2 as usize, 0, |_b: &IndirectBlock| {
+                blocks.extend_from_slice(&_b[..count]);
+            })?;
+        }
+        self.size = 0;
+        self.direct = [0; INODE_DIRECT_COUNT];
+        self.indirect1 = 0;
+        self.indirect2 = 0;
+        Ok(blocks)
+    }
+
+    pub fn read_at(
+        &self,
+        offset: usize,
+        buf: &mut [u8],
+        fs: &EasyFileSystem,
+    ) -> Result<usize, EfsError> {
+        let end = core::cmp::min(offset.saturating_add(buf.len()), self.size as usize);
+        let mut start = offset;
+        let mut pos = 0;
+        while start < end {
+            let block_end = core::cmp::min((start / BLOCK_SZ + 1) * BLOCK_SZ, end);
+            let len = block_end - start;
+            let block_id = self.get_block_id(start / BLOCK_SZ, fs)?;
+            read_at(&fs.block_device, block_id as usize, 0, |_b: &DataBlock| {
+                let inner = start % BLOCK_SZ;
+                buf[pos..pos + len].copy_from_slice(&_b[inner..inner + len]);
+            })?;
+            pos += len;
+            start = block_end;
+        }
+        Ok(pos)
+    }
+
+    // The size must have been increased to hold the data
+    pub fn write_at(&self, offset: usize, buf: &[u8], fs: &EasyFileSystem) -> Result<usize, EfsError> {
+        let end = core::cmp::min(offset.saturating_add(buf.len()), self.size as usize);
+        let mut start = offset;
+        let mut pos = 0;
+        while start < end {
+            let block_end = core::cmp::min((start / BLOCK_SZ + 1) * BLOCK_SZ, end);
+            let len = block_end - start;
+            let block_id = self.get_block_id(start / BLOCK_SZ, fs)?;
+            modify_at(&fs.block_device, block_id as usize, 0, |_b: &mut DataBlock| {
+                let inner = start % BLOCK_SZ;
+                _b[inner..inner + len].copy_from_slice(&buf[pos..pos + len]);
+            })?;
+            pos += len;
+            start = block_end;
+        }
+        Ok(pos)
+    }
+}
+
+#[repr(C)]
+pub struct DirEntry {
+    name: [u8; NAME_LENGTH_LIMIT + 1],
+    inode_number: u32,
+}
+
+impl DirEntry {
+    pub fn empty() -> Self {
+        Self {
+            name: [0; NAME_LENGTH_LIMIT + 1],
+            inode_number: 0,
+        }
+    }
+    pub fn new(name: &str, inode_number: u32) -> Self {
+        assert!(name.len() <= NAME_LENGTH_LIMIT);
+        let mut bytes = [0; NAME_LENGTH_LIMIT + 1];
+        bytes[..name.len()].copy_from_slice(name.as_bytes());
+        Self {
+            name: bytes,
+            inode_number,
+        }
+    }
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self as *const _ as *const u8, DIRENT_SZ) }
+    }
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self as *mut _ as *mut u8, DIRENT_SZ) }
+    }
+    // None if the name on the disk isn't valid UTF-8
+    pub fn name(&self) -> Option<&str> {
+        let len = self.name.iter().position(|_c| *_c == 0).unwrap_or(self.name.len());
+        core::str::from_utf8(&self.name[..len]).ok()
+    }
+    pub fn inode_number(&self) -> u32 {
+        self.inode_number
+    }
+}
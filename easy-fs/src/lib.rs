@@ -0,0 +1,28 @@
+//! A simple xv6 style filesystem shared by the kernel and the host tool.
+//!
+//! | super block | inode bitmap | inode area | data bitmap | data area |
+
+#![no_std]
+
+extern crate alloc;
+
+mod bitmap;
+mod block_dev;
+mod efs;
+mod layout;
+mod vfs;
+
+pub const BLOCK_SZ: usize = 512;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum EfsError {
+    // The block device failed
+    Io,
+    // The on-disk data is out of range
+    Corrupted,
+}
+
+pub use block_dev::BlockDevice;
+pub use efs::EasyFileSystem;
+pub use layout::DiskInodeType;
+pub use vfs::Inode;
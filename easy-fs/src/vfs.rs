@@ -0,0 +1,206 @@
+use super::{
+    block_dev::{modify_at, read_at, BlockDevice},
+    efs::EasyFileSystem,
+    layout::{DirEntry, DiskInode, DiskInodeType, DIRENT_SZ, NAME_LENGTH_LIMIT},
+    EfsError,
+};
+use alloc::{string::String, sync::Arc, vec::Vec};
+use spin::{Mutex, MutexGuard};
+
+// The in-memory handle of a disk inode.
+// Every operation holds the filesystem lock.
+pub struct Inode {
+    inode_id: u32,
+    block_id: usize,
+    block_offset: usize,
+    fs: Arc<Mutex<EasyFileSystem>>,
+}
+
+impl Inode {
+    pub fn new(
+        inode_id: u32,
+        block_id: usize,
+        block_offset: usize,
+        fs: Arc<Mutex<EasyFileSystem>>,
+    ) -> Self {
+        Self {
+            inode_id,
+            block_id,
+            block_offset,
+            fs,
+        }
+    }
+
+    fn read_disk_inode<V>(
+        &self,
+        device: &Arc<dyn BlockDevice>,
+        f: impl FnOnce(&DiskInode) -> V,
+    ) -> Result<V, EfsError> {
+        read_at(device, self.block_id, self.block_offset, f)
+    }
+
+    fn modify_disk_inode<V>(
+        &self,
+        device: &Arc<dyn BlockDevice>,
+        f: impl FnOnce(&mut DiskInode) -> V,
+    ) -> Result<V, EfsError> {
+        modify_at(device, self.block_id, self.block_offset, f)
+    }
+
+    pub fn inode_id(&self) -> u32 {
+        self.inode_id
+    }
+
+    pub fn get_type(&self) -> Result<DiskInodeType, EfsError> {
+        let fs = self.fs.lock();
+        self.read_disk_inode(&fs.block_device, |_di| _di.get_type())
+    }
+
+    pub fn size(&self) -> Result<usize, EfsError> {
+        let fs = self.fs.lock();
+        self.read_disk_inode(&fs.block_device, |_di| _di.size as usize)
+    }
+
+    // The (name, inode id) of every entry of the directory.
+    // An entry whose name isn't valid UTF-8 is skipped.
+    fn entries(&self, fs: &EasyFileSystem) -> Result<Vec<(String, u32)>, EfsError> {
+        self.read_disk_inode(&fs.block_device, |_di| {
+            let mut entries = Vec::new();
+            let mut dirent = DirEntry::empty();
+            for _i in 0.._di.size as usize / DIRENT_SZ {
+                _di.read_at(_i * DIRENT_SZ, dirent.as_bytes_mut(), fs)?;
+                if let Some(name) = dirent.name() {
+                    entries.push((String::from(name), dirent.inode_number()));
+                }
+            }
+            Ok(entries)
+        })?
+    }
+
+    fn get_inode(&self, fs: &EasyFileSystem, inode_id: u32) -> Result<Arc<Inode>, EfsError> {
+        let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id)?;
+        Ok(Arc::new(Self::new(
+            inode_id,
+            block_id,
+            block_offset,
+            self.fs.clone(),
+        )))
+    }
+
+    pub fn find(&self, name: &str) -> Result<Option<Arc<Inode>>, EfsError> {
+        let fs = self.fs.lock();
+        if !self.read_disk_inode(&fs.block_device, |_di| _di.is_dir())? {
+            return Ok(None);
+        }
+        match self.entries(&fs)?.into_iter().find(|(_name, _)| _name == name) {
+            Some((_, inode_id)) => Ok(Some(self.get_inode(&fs, inode_id)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn ls(&self) -> Result<Vec<String>, EfsError> {
+        let fs = self.fs.lock();
+        Ok(self
+            .entries(&fs)?
+            .into_iter()
+            .map(|(_name, _)| _name)
+            .collect())
+    }
+
+    // Allocate the blocks and grow the disk inode to new_size.
+    // Return false if the disk is full.
+    fn increase_size(
+        &self,
+        new_size: u32,
+        disk_inode: &mut DiskInode,
+        fs: &mut MutexGuard<EasyFileSystem>,
+    ) -> Result<bool, EfsError> {
+        if new_size <= disk_inode.size {
+            return Ok(true);
+        }
+        let mut blocks = Vec::new();
+        for _i in 0..disk_inode.blocks_num_needed(new_size) {
+            match fs.alloc_data()? {
+                Some(block) => blocks.push(block),
+                None => {
+                    for _b in blocks {
+                        fs.dealloc_data(_b)?;
+                    }
+                    return Ok(false);
+                }
+            }
+        }
+        if let Err(err) = disk_inode.increase_size(new_size, blocks.clone(), fs) {
+            // The size is unchanged, the blocks are not part of the file
+            for _b in blocks {
+                fs.dealloc_data(_b)?;
+            }
+            return Err(err);
+        }
+        Ok(true)
+    }
+
+    // Return None if the name exists, is too long or the disk is full
+    pub fn create(&self, name: &str, _type: DiskInodeType) -> Result<Option<Arc<Inode>>, EfsError> {
+        if name.is_empty() || name.len() > NAME_LENGTH_LIMIT {
+            return Ok(None);
+        }
+        let mut fs = self.fs.lock();
+        if !self.read_disk_inode(&fs.block_device, |_di| _di.is_dir())?
+            || self.entries(&fs)?.iter().any(|(_name, _)| _name == name)
+        {
+            return Ok(None);
+        }
+        let inode_id = match fs.alloc_inode()? {
+            Some(inode_id) => inode_id,
+            None => return Ok(None),
+        };
+        let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id)?;
+        modify_at(&fs.block_device, block_id, block_offset, |_di: &mut DiskInode| {
+            _di.initialize(_type);
+        })?;
+        let device = fs.block_device.clone();
+        let appended = self.modify_disk_inode(&device, |_di| {
+            let offset = _di.size as usize;
+            if !self.increase_size((offset + DIRENT_SZ) as u32, _di, &mut fs)? {
+                return Ok(false);
+            }
+            let dirent = DirEntry::new(name, inode_id);
+            _di.write_at(offset, dirent.as_bytes(), &fs)?;
+            Ok(true)
+        })??;
+        if !appended {
+            return Ok(None);
+        }
+        Ok(Some(self.get_inode(&fs, inode_id)?))
+    }
+
+    pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize, EfsError> {
+        let fs = self.fs.lock();
+        self.read_disk_inode(&fs.block_device, |_di| _di.read_at(offset, buf, &fs))?
+    }
+
+    // Write as much as the disk holds, the file grows if needed
+    pub fn write_at(&self, offset: usize, buf: &[u8]) -> Result<usize, EfsError> {
+        let mut fs = self.fs.lock();
+        let device = fs.block_device.clone();
+        self.modify_disk_inode(&device, |_di| {
+            let end = core::cmp::min(offset.saturating_add(buf.len()), DiskInode::max_size());
+            if end > offset && !self.increase_size(end as u32, _di, &mut fs)? {
+                return Ok(0);
+            }
+            _di.write_at(offset, buf, &fs)
+        })?
+    }
+
+    // Free every data block of the file
+    pub fn clear(&self) -> Result<(), EfsError> {
+        let mut fs = self.fs.lock();
+        let device = fs.block_device.clone();
+        let blocks = self.modify_disk_inode(&device, |_di| _di.clear_size(&fs))??;
+        for _b in blocks {
+            fs.dealloc_data(_b)?;
+        }
+        Ok(())
+    }
+}
@@ -1,15 +1,31 @@
 use crate::{
-    addr_type::PhysAddr,
-    arch::paging::{PageTable, PageTableFlagsField},
-    frame::{DataFrame, FrameObj, FrameSize},
+    addr_type::{PhysAddr, UserAddr},
+    consts::{USER_MMAP_BASE, USER_SPACE_END},
+    arch::{
+        flush_tlb, flush_tlb_va,
+        paging::{is_user_readable, is_user_writable, PageTable, PageTableFlags, PageTableFlagsField},
+        PAGE_SIZE,
+    },
+    frame::{DataFrame, FrameObj, FrameSize, GuardFrame, LazyFrame},
     frame_allocator::{FrameAllocator, CURRENT_FRAME_ALLOCATOR},
 };
-use alloc::vec::Vec;
+use alloc::{string::String, sync::Arc, vec, vec::Vec};
 use core::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     fmt::{self, Debug, Formatter},
 };
 
+// Lazy frames for len bytes, None if the kernel heap can't hold them
+pub fn lazy_frames(len: u64) -> Option<Vec<FrameObj>> {
+    let count = (len / PAGE_SIZE) as usize;
+    let mut frames = Vec::new();
+    frames.try_reserve_exact(count).ok()?;
+    for _i in 0..count {
+        frames.push(FrameObj::Lazy(LazyFrame::new(FrameSize::Size4Kb)));
+    }
+    Some(frames)
+}
+
 /*
 pub trait PageTableInterface:FromBytes{
     fn map(&mut self,region:&VmRegion);
@@ -20,11 +36,32 @@ pub trait PageTableInterface:FromBytes{
 pub struct VmSpace {
     regions: RefCell<Vec<VmRegion>>,
     page_table: DataFrame,
+    // The stack region starts at stack_bottom and
+    // may grow down to stack_limit
+    stack_bottom: Cell<u64>,
+    stack_limit: u64,
+    // The heap region is [heap_base, brk)
+    heap_base: u64,
+    brk: u64,
 }
 
+#[derive(core::fmt::Debug)]
 pub enum AccessSpaceError {
     UnExisted,
     LazyAlloced,
+    // The range splits a huge frame
+    UnAligned,
+    // The region doesn't allow the access of the user
+    PermissionDenied,
+    // Not valid UTF-8
+    InvalidStr,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, core::fmt::Debug)]
+pub enum AccessType {
+    Read,
+    Write,
+    Exec,
 }
 
 impl VmSpace {
@@ -38,6 +75,10 @@ impl VmSpace {
         Self {
             regions: RefCell::new(Vec::new()),
             page_table: page_table,
+            stack_bottom: Cell::new(0),
+            stack_limit: 0,
+            heap_base: 0,
+            brk: 0,
         }
     }
     pub fn map_range(
@@ -59,6 +100,76 @@ impl VmSpace {
             .map(&region);
         self.regions.borrow_mut().push(region)
     }
+    // The region at stack_bottom is the stack
+    pub fn set_stack(&mut self, stack_bottom: u64, stack_limit: u64) {
+        self.stack_bottom.set(stack_bottom);
+        self.stack_limit = stack_limit;
+    }
+    pub fn init_heap(&mut self, heap_base: u64) {
+        self.heap_base = heap_base;
+        self.brk = heap_base;
+    }
+    pub fn get_brk(&self) -> u64 {
+        self.brk
+    }
+    // Move the program break, the new pages of the heap are lazy.
+    // The heap stays below the stack and the mmap area.
+    // Return None if the heap can't be moved there.
+    pub fn set_brk(&mut self, brk: u64) -> Option<u64> {
+        let limit = if self.stack_limit > self.heap_base {
+            self.stack_limit.min(USER_MMAP_BASE)
+        } else {
+            USER_MMAP_BASE
+        };
+        if brk < self.heap_base || brk > limit {
+            return None;
+        }
+        let old_end = self.brk.checked_add(PAGE_SIZE - 1)? & !(PAGE_SIZE - 1);
+        let new_end = brk.checked_add(PAGE_SIZE - 1)? & !(PAGE_SIZE - 1);
+        if new_end > old_end {
+            let len = new_end - old_end;
+            if !self.is_free(old_end, len) {
+                return None;
+            }
+            let frames = lazy_frames(len)?;
+            let grown = VmRegion {
+                vaddr: old_end,
+                size: len,
+                frames: frames,
+                flag: Some(
+                    PageTableFlags::ATTR_INDEX.val(0)
+                        + PageTableFlags::SH::INNERSHARE
+                        + PageTableFlags::AP::EL0_RW_ELX_RW
+                        + PageTableFlags::UXN::SET
+                        + PageTableFlags::PXN::SET
+                        + PageTableFlags::AF::SET,
+                ),
+            };
+            self.page_table
+                .as_type_mut::<PageTable>(0)
+                .unwrap()
+                .map(&grown);
+            // Extend the heap region if there is one
+            let heap_base = self.heap_base;
+            let heap = if old_end > heap_base {
+                self.find_region_mut(old_end - 1)
+                    .filter(|_r| _r.start() >= heap_base)
+            } else {
+                None
+            };
+            match heap {
+                Some(region) => {
+                    region.size += grown.size;
+                    region.frames.extend(grown.frames);
+                }
+                None => self.regions.get_mut().push(grown),
+            }
+        } else if new_end < old_end {
+            self.unmap(new_end, old_end - new_end).ok()?;
+        }
+        self.brk = brk;
+        Some(brk)
+    }
     pub fn get_pagetable(&self) -> PhysAddr {
         self.page_table.frame_addr()
     }
@@ -67,50 +178,408 @@ impl VmSpace {
         println!("Addr:{:?}\n{:?}", self.get_pagetable(), pg);
     }
 
-    pub fn read_from_space(&self, buf: &mut [u8], va: u64) -> Result<usize, AccessSpaceError> {
-        for _region in self.regions.borrow().iter() {
-            if _region.is_in_range(va) {
-                let mut off = va - _region.start();
-                let mut len = buf.len() as u64;
-                let mut pos = 0;
-                for _frame in _region.frames.iter() {
-                    let frame_size = _frame.frame_size() as u64;
-                    if len > 0 && off < frame_size {
-                        off = 0;
-                        match _frame {
-                            FrameObj::Data(data) => {
-                                let l = if (frame_size - off) < len {
-                                    frame_size - off
-                                } else {
-                                    len
-                                };
-                                let src = data.as_slice::<u8>(off, l).unwrap();
-                                let dst = &mut buf[pos as usize..(pos + l) as usize];
-                                dst.copy_from_slice(src);
-                                pos += l;
-                                len -= l;
-                            }
-                            _ => {
-                                return Err(AccessSpaceError::UnExisted);
-                            }
-                        }
-                    } else {
-                        off = off - frame_size;
+    // Check that the user is allowed to access [va, va + len)
+    pub fn check_access(
+        &self,
+        va: u64,
+        len: u64,
+        access: AccessType,
+    ) -> Result<(), AccessSpaceError> {
+        let end = va.checked_add(len).ok_or(AccessSpaceError::UnExisted)?;
+        let regions = self.regions.borrow();
+        let mut addr = va;
+        while addr < end {
+            let region = regions
+                .iter()
+                .find(|_r| _r.is_in_range(addr))
+                .ok_or(AccessSpaceError::UnExisted)?;
+            let allowed = match access {
+                AccessType::Write => is_user_writable(region.flag()),
+                AccessType::Read | AccessType::Exec => is_user_readable(region.flag()),
+            };
+            if !allowed {
+                return Err(AccessSpaceError::PermissionDenied);
+            }
+            addr = region.end();
+        }
+        Ok(())
+    }
+
+    // Run f on the frame holding va with the offset of va in
+    // the frame and the number of bytes left in the frame
+    fn with_frame<R>(
+        &self,
+        va: u64,
+        f: impl FnOnce(&FrameObj, u64, u64) -> R,
+    ) -> Result<R, AccessSpaceError> {
+        let regions = self.regions.borrow();
+        let region = regions
+            .iter()
+            .find(|_r| _r.is_in_range(va))
+            .ok_or(AccessSpaceError::UnExisted)?;
+        let (idx, frame_va) = region.frame_index(va).ok_or(AccessSpaceError::UnExisted)?;
+        let frame = &region.frames[idx];
+        let off = va - frame_va;
+        Ok(f(frame, off, frame.frame_size() as u64 - off))
+    }
+
+    pub fn copy_from_user(&self, buf: &mut [u8], va: u64) -> Result<(), AccessSpaceError> {
+        self.check_access(va, buf.len() as u64, AccessType::Read)?;
+        let mut pos = 0;
+        while pos < buf.len() {
+            let copied = self.with_frame(va + pos as u64, |frame, off, left| {
+                let l = core::cmp::min(left, (buf.len() - pos) as u64);
+                let dst = &mut buf[pos..pos + l as usize];
+                match frame {
+                    FrameObj::Data(data) => dst.copy_from_slice(data.as_slice::<u8>(off, l).unwrap()),
+                    FrameObj::Cow(data) => dst.copy_from_slice(data.as_slice::<u8>(off, l).unwrap()),
+                    // Never written yet
+                    FrameObj::Lazy(_) => dst.fill(0),
+                    FrameObj::Guard(_) => return Err(AccessSpaceError::UnExisted),
+                }
+                Ok(l as usize)
+            })??;
+            pos += copied;
+        }
+        Ok(())
+    }
+
+    pub fn copy_to_user(&self, buf: &[u8], va: u64) -> Result<(), AccessSpaceError> {
+        self.check_access(va, buf.len() as u64, AccessType::Write)?;
+        // Back the lazy frames and give this space
+        // its own copy of the shared frames first
+        let mut addr = va & !(PAGE_SIZE - 1);
+        while addr < va + buf.len() as u64 {
+            self.handle_page_fault(addr, AccessType::Write);
+            addr += PAGE_SIZE;
+        }
+        let mut pos = 0;
+        while pos < buf.len() {
+            let copied = self.with_frame(va + pos as u64, |frame, off, left| {
+                let l = core::cmp::min(left, (buf.len() - pos) as u64);
+                match frame {
+                    FrameObj::Data(data) => data
+                        .as_slice_mut::<u8>(off, l)
+                        .unwrap()
+                        .copy_from_slice(&buf[pos..pos + l as usize]),
+                    // Failed to back the frame
+                    FrameObj::Lazy(_) | FrameObj::Cow(_) => {
+                        return Err(AccessSpaceError::LazyAlloced)
                     }
+                    FrameObj::Guard(_) => return Err(AccessSpaceError::UnExisted),
                 }
+                Ok(l as usize)
+            })??;
+            pos += copied;
+        }
+        Ok(())
+    }
+
+    // Read a string of len bytes which is not NUL terminated
+    pub fn read_user_str(&self, va: u64, len: u64) -> Result<String, AccessSpaceError> {
+        self.check_access(va, len, AccessType::Read)?;
+        let mut buf = vec![0u8; len as usize];
+        self.copy_from_user(&mut buf, va)?;
+        String::from_utf8(buf).map_err(|_| AccessSpaceError::InvalidStr)
+    }
+
+    // Drop every region and the tables under the root
+    pub fn clear(&mut self) {
+        self.regions.borrow_mut().clear();
+        self.set_stack(0, 0);
+        self.init_heap(0);
+        self.page_table
+            .as_type_mut::<PageTable>(0)
+            .unwrap()
+            .release();
+    }
+
+    // Clone the space for a forked process.
+    // The data frames become COW frames shared by both spaces,
+    // so the page table of this space is remapped read-only as well.
+    pub fn fork(&self) -> Self {
+        let mut space = VmSpace::new();
+        let pg = self.page_table.as_type_mut::<PageTable>(0).unwrap();
+        for _region in self.regions.borrow_mut().iter_mut() {
+            let frames = _region.share_frames();
+            pg.map(_region);
+            space.map_range(_region.start(), _region.size(), frames, _region.flag());
+        }
+        space.set_stack(self.stack_bottom.get(), self.stack_limit);
+        space.heap_base = self.heap_base;
+        space.brk = self.brk;
+        flush_tlb();
+        space
+    }
+
+    // Try to resolve a fault of the user at va.
+    // Return false if the access is illegal.
+    pub fn handle_page_fault(&self, va: u64, access: AccessType) -> bool {
+        let writable = match self.regions.borrow().iter().find(|_r| _r.is_in_range(va)) {
+            Some(region) => is_user_writable(region.flag()),
+            // The grown pages are lazy like the rest of the stack
+            None => return self.grow_stack(va) && self.lazy_fault(va),
+        };
+        if access == AccessType::Write && !writable {
+            return false;
+        }
+        if self.lazy_fault(va) {
+            return true;
+        }
+        access == AccessType::Write && self.cow_fault(va)
+    }
+
+    // Back the lazy frame at va with zeroed data frames.
+    // Return false if va isn't in a lazy frame.
+    fn lazy_fault(&self, va: u64) -> bool {
+        let mut regions = self.regions.borrow_mut();
+        let region = match regions.iter_mut().find(|_r| _r.is_in_range(va)) {
+            Some(region) => region,
+            None => return false,
+        };
+        let (idx, frame_va) = match region.frame_index(va) {
+            Some((idx, frame_va)) => (idx, frame_va),
+            None => return false,
+        };
+        let size = match &region.frames[idx] {
+            FrameObj::Lazy(lazy) => lazy.frame_size() as u64,
+            _ => return false,
+        };
+        let new_frames = match CURRENT_FRAME_ALLOCATOR
+            .exclusive_access()
+            .allocate_frames(UserAddr::new(frame_va), size)
+        {
+            Ok(frames) => frames,
+            Err(_) => return false,
+        };
+        let mut frames = Vec::new();
+        for _f in new_frames.into_iter() {
+            _f.zero();
+            frames.push(FrameObj::Data(_f));
+        }
+        let backed = VmRegion {
+            vaddr: frame_va,
+            size: size,
+            frames: frames,
+            flag: region.flag(),
+        };
+        self.page_table
+            .as_type_mut::<PageTable>(0)
+            .unwrap()
+            .map(&backed);
+        region.frames.splice(idx..idx + 1, backed.frames);
+        true
+    }
+
+    // Extend the stack region down to the page of va,
+    // the new pages are lazy and backed on the first access
+    fn grow_stack(&self, va: u64) -> bool {
+        let bottom = self.stack_bottom.get();
+        if va >= bottom || va < self.stack_limit {
+            return false;
+        }
+        let new_bottom = va & !(PAGE_SIZE - 1);
+        let mut regions = self.regions.borrow_mut();
+        let region = match regions.iter_mut().find(|_r| _r.start() == bottom) {
+            Some(region) => region,
+            None => return false,
+        };
+        let mut frames = Vec::new();
+        for _i in 0..(bottom - new_bottom) / PAGE_SIZE {
+            frames.push(FrameObj::Lazy(LazyFrame::new(FrameSize::Size4Kb)));
+        }
+        let grown = VmRegion {
+            vaddr: new_bottom,
+            size: bottom - new_bottom,
+            frames: frames,
+            flag: region.flag(),
+        };
+        self.page_table
+            .as_type_mut::<PageTable>(0)
+            .unwrap()
+            .map(&grown);
+        region.vaddr = new_bottom;
+        region.size += grown.size;
+        region.frames.splice(0..0, grown.frames);
+        self.stack_bottom.set(new_bottom);
+        true
+    }
+
+    // Resolve a write to a COW frame by giving this space a private copy.
+    // The frame is reused when no other space shares it any more.
+    // Return false if va isn't in a writable COW frame.
+    pub fn cow_fault(&self, va: u64) -> bool {
+        let mut regions = self.regions.borrow_mut();
+        let region = match regions.iter_mut().find(|_r| _r.is_in_range(va)) {
+            Some(region) => region,
+            None => return false,
+        };
+        if !is_user_writable(region.flag()) {
+            return false;
+        }
+        let (idx, frame_va) = match region.frame_index(va) {
+            Some((idx, frame_va)) => (idx, frame_va),
+            None => return false,
+        };
+        if !matches!(region.frames[idx], FrameObj::Cow(_)) {
+            return false;
+        }
+        let size = region.frames[idx].frame_size();
+        let shared = match core::mem::replace(
+            &mut region.frames[idx],
+            FrameObj::Guard(GuardFrame::new(size)),
+        ) {
+            FrameObj::Cow(shared) => shared,
+            _ => unreachable!(),
+        };
+        let frames = match Arc::try_unwrap(shared) {
+            Ok(data) => vec![FrameObj::Data(data)],
+            Err(shared) => {
+                let new_frames = CURRENT_FRAME_ALLOCATOR
+                    .exclusive_access()
+                    .allocate_frames(UserAddr::new(frame_va), size as u64)
+                    .unwrap();
+                let mut off = 0;
+                let mut frames = Vec::new();
+                for _f in new_frames.into_iter() {
+                    let l = _f.frame_size() as u64;
+                    _f.as_slice_mut::<u8>(0, l)
+                        .unwrap()
+                        .copy_from_slice(shared.as_slice::<u8>(off, l).unwrap());
+                    off += l;
+                    frames.push(FrameObj::Data(_f));
+                }
+                frames
             }
+        };
+        let private = VmRegion {
+            vaddr: frame_va,
+            size: size as u64,
+            frames: frames,
+            flag: region.flag(),
+        };
+        self.page_table
+            .as_type_mut::<PageTable>(0)
+            .unwrap()
+            .map(&private);
+        region.frames.splice(idx..idx + 1, private.frames);
+        flush_tlb();
+        true
+    }
+
+    // Whether [va, va + len) is neither mapped nor reserved for the stack
+    pub fn is_free(&self, va: u64, len: u64) -> bool {
+        let end = match va.checked_add(len) {
+            Some(end) => end,
+            None => return false,
+        };
+        if end > USER_SPACE_END || (va < self.stack_bottom.get() && end > self.stack_limit) {
+            return false;
         }
-        Ok(0)
+        !self
+            .regions
+            .borrow()
+            .iter()
+            .any(|_r| va < _r.end() && end > _r.start())
     }
 
-    pub fn find_region_mut(&self, va: u64) -> &mut VmRegion {
-        todo!()
+    // Find a free range of len above USER_MMAP_BASE
+    pub fn find_free_area(&self, len: u64) -> Option<u64> {
+        let mut va = USER_MMAP_BASE;
+        loop {
+            let end = va.checked_add(len)?;
+            if end > USER_SPACE_END {
+                return None;
+            }
+            let next = self
+                .regions
+                .borrow()
+                .iter()
+                .filter(|_r| va < _r.end() && end > _r.start())
+                .map(|_r| _r.end())
+                .max();
+            match next {
+                // Try again after the overlapped regions
+                Some(next) => va = next.checked_add(PAGE_SIZE - 1)? & !(PAGE_SIZE - 1),
+                None => return Some(va),
+            }
+        }
     }
-    pub fn unmap(&self, region: &mut VmRegion) {
-        todo!()
+
+    pub fn find_region_mut(&mut self, va: u64) -> Option<&mut VmRegion> {
+        self.regions.get_mut().iter_mut().find(|_r| _r.is_in_range(va))
     }
-    pub fn remap(&self, region: &mut VmRegion) {
-        todo!()
+
+    // Split the regions so that va and va + len become region boundaries.
+    // Only the boundaries between frames can be split.
+    fn isolate(&mut self, va: u64, len: u64) -> Result<(), AccessSpaceError> {
+        let end = va.checked_add(len).ok_or(AccessSpaceError::UnExisted)?;
+        let regions = self.regions.get_mut();
+        if !regions
+            .iter()
+            .all(|_r| _r.is_boundary(va) && _r.is_boundary(end))
+        {
+            return Err(AccessSpaceError::UnAligned);
+        }
+        for _at in [va, end] {
+            let mut splited = Vec::new();
+            for _region in regions.iter_mut() {
+                if _at > _region.start() && _at < _region.end() {
+                    splited.push(_region.split_off(_at));
+                }
+            }
+            regions.append(&mut splited);
+        }
+        Ok(())
+    }
+
+    // Remove the mappings in [va, va + len)
+    pub fn unmap(&mut self, va: u64, len: u64) -> Result<(), AccessSpaceError> {
+        self.isolate(va, len)?;
+        let end = va + len;
+        let pg = self.page_table.as_type_mut::<PageTable>(0).unwrap();
+        let regions = self.regions.get_mut();
+        let mut pos = 0;
+        while pos < regions.len() {
+            let region = &regions[pos];
+            if region.start() >= va && region.end() <= end {
+                pg.unmap(region);
+                region.flush_tlb();
+                // The frames go back to the allocator here
+                regions.remove(pos);
+            } else {
+                pos += 1;
+            }
+        }
+        Ok(())
+    }
+
+    // Change the flag of the mappings in [va, va + len)
+    pub fn remap(
+        &mut self,
+        va: u64,
+        len: u64,
+        flag: PageTableFlagsField,
+    ) -> Result<(), AccessSpaceError> {
+        self.isolate(va, len)?;
+        let end = va + len;
+        let pg = self.page_table.as_type_mut::<PageTable>(0).unwrap();
+        for _region in self.regions.get_mut().iter_mut() {
+            if _region.start() >= va && _region.end() <= end {
+                _region.replace_flag(flag);
+                pg.map(_region);
+                _region.flush_tlb();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for VmSpace {
+    // Release the tables under the root,
+    // the root itself is freed as a DataFrame
+    fn drop(&mut self) {
+        self.clear();
     }
 }
 
@@ -140,6 +609,77 @@ impl VmRegion {
     pub fn flag(&self) -> Option<PageTableFlagsField> {
         self.flag
     }
+    // Turn the data frames into COW frames and
+    // return the frames for the region of a forked space
+    pub fn share_frames(&mut self) -> Vec<FrameObj> {
+        let mut frames = Vec::new();
+        for _frame in self.frames.iter_mut() {
+            let size = _frame.frame_size();
+            let shared = match _frame {
+                FrameObj::Data(_) => {
+                    let data = match core::mem::replace(_frame, FrameObj::Guard(GuardFrame::new(size))) {
+                        FrameObj::Data(data) => Arc::new(data),
+                        _ => unreachable!(),
+                    };
+                    *_frame = FrameObj::Cow(data.clone());
+                    FrameObj::Cow(data)
+                }
+                FrameObj::Cow(data) => FrameObj::Cow(data.clone()),
+                FrameObj::Guard(_) => FrameObj::Guard(GuardFrame::new(size)),
+                FrameObj::Lazy(_) => FrameObj::Lazy(LazyFrame::new(size)),
+            };
+            frames.push(shared);
+        }
+        frames
+    }
+    pub fn end(&self) -> u64 {
+        self.vaddr + self.size
+    }
+    // Whether the region can be split at va
+    pub fn is_boundary(&self, va: u64) -> bool {
+        if va <= self.start() || va >= self.end() {
+            return true;
+        }
+        match self.frame_index(va) {
+            Some((_, frame_va)) => frame_va == va,
+            None => false,
+        }
+    }
+    // Split the region at a frame boundary.
+    // self keeps [start, at) and the rest is returned.
+    pub fn split_off(&mut self, at: u64) -> VmRegion {
+        let (idx, frame_va) = self.frame_index(at).unwrap();
+        assert_eq!(frame_va, at, "split a frame of the region");
+        let frames = self.frames.split_off(idx);
+        let right = VmRegion {
+            vaddr: at,
+            size: self.end() - at,
+            frames: frames,
+            flag: self.flag,
+        };
+        self.size = at - self.vaddr;
+        right
+    }
+    // Drop the stale translations of the region
+    pub fn flush_tlb(&self) {
+        let mut va = self.vaddr;
+        for _frame in self.frames.iter() {
+            flush_tlb_va(va);
+            va += _frame.frame_size() as u64;
+        }
+    }
+    // Return the index of the frame containing va and the start of the frame
+    pub fn frame_index(&self, va: u64) -> Option<(usize, u64)> {
+        let mut frame_va = self.vaddr;
+        for (_pos, _frame) in self.frames.iter().enumerate() {
+            let sz = _frame.frame_size() as u64;
+            if va >= frame_va && va < frame_va + sz {
+                return Some((_pos, frame_va));
+            }
+            frame_va += sz;
+        }
+        None
+    }
     pub fn is_in_range(&self, va: u64) -> bool {
         let b = self.vaddr;
         let e = self.vaddr + self.size;
@@ -4,6 +4,19 @@ use cortex_a::asm;
 // Public Code
 //--------------------------------------------------------------------------------------------------
 
+/// Sleep until the next interrupt arrives.
+/// The irqs are unmasked while waiting and masked again on return.
+#[inline(always)]
+pub fn wait_for_irq() {
+    unsafe {
+        super::enable_irq();
+    }
+    asm::wfi();
+    unsafe {
+        super::disable_irq();
+    }
+}
+
 /// Pause execution on the core.
 #[inline(always)]
 pub fn wait_forever() -> ! {
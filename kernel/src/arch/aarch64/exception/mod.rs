@@ -1,11 +1,12 @@
 use cortex_a::registers::*;
 use tock_registers::interfaces::Readable;
 
-use self::syscall_wrapper::syscall_router;
+use self::{page_fault::page_fault_handler, syscall_wrapper::syscall_router};
 
 core::arch::global_asm!(include_str!("exception_table.S"));
 core::arch::global_asm!(include_str!("irq.S"));
 
+mod page_fault;
 mod syscall_wrapper;
 
 extern "C" {
@@ -24,6 +25,7 @@ pub fn exception_init() {
 #[no_mangle]
 extern "C" fn irq_handler() {
     crate::driver::gic::gicvc2_handler();
+    crate::thread::exit_if_killed();
     unsafe {
         enable_irq();
     }
@@ -44,7 +46,8 @@ extern "C" fn syn_exception_handler(sp: u64) {
     let esr_ec = ESR_EL1.read_as_enum(ESR_EL1::EC);
     match esr_ec {
         Some(ESR_EL1::EC::Value::SVC64) => syscall_router(sp),
-        Some(ESR_EL1::EC::Value::DataAbortLowerEL) => panic!("Unsupported DataAbortLowerEL"),
+        Some(ESR_EL1::EC::Value::DataAbortLowerEL) => page_fault_handler(false),
+        Some(ESR_EL1::EC::Value::InstrAbortLowerEL) => page_fault_handler(true),
         Some(other) => panic!(
             "Other exception:{:#b} FaultAddr: {:#x} ExceptionLinkAddr: {:#x}",
             other as usize,
@@ -54,3 +57,4 @@ extern "C" fn syn_exception_handler(sp: u64) {
         None => panic!("None"),
     }
 }
+
@@ -0,0 +1,76 @@
+use cortex_a::registers::*;
+use tock_registers::interfaces::Readable;
+
+use crate::{
+    addr_space::AccessType,
+    println,
+    thread::{_exit, CURRENT_CPU},
+};
+
+// Exit code of the process killed by an illegal access
+const EXIT_PAGE_FAULT: i32 = -2;
+
+#[derive(core::fmt::Debug)]
+enum FaultStatus {
+    AddressSize,
+    Translation,
+    AccessFlag,
+    Permission,
+    Other(u64),
+}
+
+// Decode the fault status code (DFSC/IFSC) of ISS
+fn fault_status(iss: u64) -> FaultStatus {
+    match (iss & 0x3f) >> 2 {
+        0b0000 => FaultStatus::AddressSize,
+        0b0001 => FaultStatus::Translation,
+        0b0010 => FaultStatus::AccessFlag,
+        0b0011 => FaultStatus::Permission,
+        _ => FaultStatus::Other(iss & 0x3f),
+    }
+}
+
+// Abort from user mode.
+// Resolve the fault in the space of the current thread,
+// kill the process if the access is illegal.
+pub fn page_fault_handler(is_instr: bool) {
+    let far = FAR_EL1.get();
+    let elr = ELR_EL1.get();
+    let iss = ESR_EL1.read(ESR_EL1::ISS);
+    let status = fault_status(iss);
+    let access = if is_instr {
+        AccessType::Exec
+    } else if iss & (1 << 6) != 0 {
+        // ISS.WnR: caused by a write
+        AccessType::Write
+    } else {
+        AccessType::Read
+    };
+    let resolved = match status {
+        FaultStatus::Translation | FaultStatus::Permission => CURRENT_CPU
+            .try_get()
+            .expect("No init")
+            .exclusive_access()
+            .cur_thread()
+            .get_space()
+            .exclusive_access()
+            .handle_page_fault(far, access),
+        _ => false,
+    };
+    if resolved {
+        return;
+    }
+    let pid = CURRENT_CPU
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .cur_thread()
+        .get_process()
+        .exclusive_access()
+        .pid();
+    println!(
+        "[kernel] pid {} killed: {:?} fault on {:?} FaultAddr: {:#x} ExceptionLinkAddr: {:#x}",
+        pid, access, status, far, elr
+    );
+    _exit(EXIT_PAGE_FAULT);
+}
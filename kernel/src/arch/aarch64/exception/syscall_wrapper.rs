@@ -1,19 +1,40 @@
 use crate::{
     addr_type::{Addr, UserAddr},
     arch::{RegType, UserCtx},
-    syscall::{sys_read, sys_write},
-    thread::CURRENT_CPU,
+    syscall::{
+        sys_brk, sys_clock_gettime, sys_close, sys_dup, sys_dup3, sys_exec, sys_exit, sys_fork,
+        sys_getdents64, sys_getpid, sys_gettimeofday, sys_ioctl, sys_mmap, sys_mprotect,
+        sys_munmap, sys_nanosleep, sys_open, sys_pipe2, sys_read, sys_setpriority, sys_spawn,
+        sys_sync, sys_waitpid, sys_write, sys_yield,
+    },
+    thread::{exit_if_killed, CURRENT_CPU},
 };
 
+const SYSCALL_DUP: usize = 23;
+const SYSCALL_DUP3: usize = 24;
+const SYSCALL_IOCTL: usize = 29;
+const SYSCALL_GETDENTS64: usize = 61;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
+const SYSCALL_SYNC: usize = 81;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_NANOSLEEP: usize = 101;
+const SYSCALL_CLOCK_GETTIME: usize = 113;
 const SYSCALL_OPEN: usize = 56;
 const SYSCALL_CLOSE: usize = 57;
+const SYSCALL_PIPE2: usize = 59;
 const SYSCALL_YIELD: usize = 124;
+const SYSCALL_SETPRIORITY: usize = 140;
+const SYSCALL_GETTIMEOFDAY: usize = 169;
 const SYSCALL_WAITPID: usize = 260;
 const SYSCALL_GETPID: usize = 172;
 const SYSCALL_SWPAN: usize = 170;
+const SYSCALL_FORK: usize = 220;
+const SYSCALL_EXEC: usize = 221;
+const SYSCALL_BRK: usize = 214;
+const SYSCALL_MUNMAP: usize = 215;
+const SYSCALL_MMAP: usize = 222;
+const SYSCALL_MPROTECT: usize = 226;
 
 pub fn syscall_router(sp: u64) {
     let ksp = CURRENT_CPU
@@ -24,14 +45,47 @@ pub fn syscall_router(sp: u64) {
         .get_kernel_stack();
     assert_eq!(sp, ksp.addr());
     let kernel_stack = unsafe { &*(ksp.addr() as *mut UserCtx) };
-    let r = match kernel_stack[RegType::X8] as usize {
-        SYSCALL_EXIT => panic!("Syscall exit"),
+    let nr = kernel_stack[RegType::X8] as usize;
+    let r = match nr {
+        SYSCALL_EXIT => exit_wrapper(kernel_stack),
         SYSCALL_WRITE => write_wrapper(kernel_stack),
         SYSCALL_READ => read_wraper(kernel_stack),
+        SYSCALL_YIELD => sys_yield(),
+        SYSCALL_GETPID => sys_getpid(),
+        SYSCALL_WAITPID => waitpid_wrapper(kernel_stack),
+        SYSCALL_SWPAN => spawn_wrapper(kernel_stack),
+        SYSCALL_FORK => sys_fork(),
+        SYSCALL_EXEC => exec_wrapper(kernel_stack),
+        SYSCALL_BRK => brk_wrapper(kernel_stack),
+        SYSCALL_MMAP => mmap_wrapper(kernel_stack),
+        SYSCALL_MUNMAP => munmap_wrapper(kernel_stack),
+        SYSCALL_MPROTECT => mprotect_wrapper(kernel_stack),
+        SYSCALL_IOCTL => ioctl_wrapper(kernel_stack),
+        SYSCALL_OPEN => open_wrapper(kernel_stack),
+        SYSCALL_CLOSE => sys_close(kernel_stack[RegType::X0]),
+        SYSCALL_DUP => sys_dup(kernel_stack[RegType::X0]),
+        SYSCALL_DUP3 => dup3_wrapper(kernel_stack),
+        SYSCALL_SYNC => sys_sync(),
+        SYSCALL_GETDENTS64 => getdents64_wrapper(kernel_stack),
+        SYSCALL_PIPE2 => pipe2_wrapper(kernel_stack),
+        SYSCALL_SETPRIORITY => setpriority_wrapper(kernel_stack),
+        SYSCALL_NANOSLEEP => nanosleep_wrapper(kernel_stack),
+        SYSCALL_CLOCK_GETTIME => clock_gettime_wrapper(kernel_stack),
+        SYSCALL_GETTIMEOFDAY => gettimeofday_wrapper(kernel_stack),
         _ => panic!("Unsupport Syscall type"),
     };
-    let kernel_stack = unsafe { &mut *(ksp.addr() as *mut UserCtx) };
-    kernel_stack[RegType::X0] = r as u64;
+    // A successful exec has set up a fresh context for the new program,
+    // its X0 holds argc and must not be overwritten
+    if !(nr == SYSCALL_EXEC && r == 0) {
+        let kernel_stack = unsafe { &mut *(ksp.addr() as *mut UserCtx) };
+        kernel_stack[RegType::X0] = r as u64;
+    }
+    exit_if_killed();
+}
+
+pub fn exit_wrapper(ctx: &UserCtx) -> ! {
+    let exit_code = ctx[RegType::X0] as i32;
+    sys_exit(exit_code)
 }
 
 pub fn write_wrapper(ctx: &UserCtx) -> i64 {
@@ -47,3 +101,107 @@ pub fn read_wraper(ctx: &UserCtx) -> i64 {
     let buf_addr = UserAddr::new(ctx[RegType::X1]);
     sys_read(fd, buf_addr, buf_len)
 }
+
+pub fn waitpid_wrapper(ctx: &UserCtx) -> i64 {
+    let pid = ctx[RegType::X0] as i64;
+    let exit_code_addr = UserAddr::new(ctx[RegType::X1]);
+    sys_waitpid(pid, exit_code_addr)
+}
+
+pub fn spawn_wrapper(ctx: &UserCtx) -> i64 {
+    let path_addr = UserAddr::new(ctx[RegType::X0]);
+    let path_len = ctx[RegType::X1];
+    sys_spawn(path_addr, path_len)
+}
+
+pub fn exec_wrapper(ctx: &UserCtx) -> i64 {
+    let path_addr = UserAddr::new(ctx[RegType::X0]);
+    let path_len = ctx[RegType::X1];
+    let args_addr = UserAddr::new(ctx[RegType::X2]);
+    let argc = ctx[RegType::X3];
+    sys_exec(path_addr, path_len, args_addr, argc)
+}
+
+pub fn brk_wrapper(ctx: &UserCtx) -> i64 {
+    let addr = UserAddr::new(ctx[RegType::X0]);
+    sys_brk(addr)
+}
+
+pub fn mmap_wrapper(ctx: &UserCtx) -> i64 {
+    let addr = UserAddr::new(ctx[RegType::X0]);
+    let len = ctx[RegType::X1];
+    let prot = ctx[RegType::X2];
+    sys_mmap(addr, len, prot)
+}
+
+pub fn munmap_wrapper(ctx: &UserCtx) -> i64 {
+    let addr = UserAddr::new(ctx[RegType::X0]);
+    let len = ctx[RegType::X1];
+    sys_munmap(addr, len)
+}
+
+pub fn mprotect_wrapper(ctx: &UserCtx) -> i64 {
+    let addr = UserAddr::new(ctx[RegType::X0]);
+    let len = ctx[RegType::X1];
+    let prot = ctx[RegType::X2];
+    sys_mprotect(addr, len, prot)
+}
+
+pub fn ioctl_wrapper(ctx: &UserCtx) -> i64 {
+    let fd = ctx[RegType::X0];
+    let cmd = ctx[RegType::X1];
+    let arg = ctx[RegType::X2];
+    sys_ioctl(fd, cmd, arg)
+}
+
+pub fn open_wrapper(ctx: &UserCtx) -> i64 {
+    let path_addr = UserAddr::new(ctx[RegType::X0]);
+    let path_len = ctx[RegType::X1];
+    let flags = ctx[RegType::X2] as u32;
+    sys_open(path_addr, path_len, flags)
+}
+
+pub fn dup3_wrapper(ctx: &UserCtx) -> i64 {
+    let old_fd = ctx[RegType::X0];
+    let new_fd = ctx[RegType::X1];
+    let flags = ctx[RegType::X2];
+    sys_dup3(old_fd, new_fd, flags)
+}
+
+pub fn getdents64_wrapper(ctx: &UserCtx) -> i64 {
+    let fd = ctx[RegType::X0];
+    let buf_addr = UserAddr::new(ctx[RegType::X1]);
+    let buf_len = ctx[RegType::X2];
+    sys_getdents64(fd, buf_addr, buf_len)
+}
+
+pub fn pipe2_wrapper(ctx: &UserCtx) -> i64 {
+    let fds_addr = UserAddr::new(ctx[RegType::X0]);
+    let flags = ctx[RegType::X1];
+    sys_pipe2(fds_addr, flags)
+}
+
+pub fn setpriority_wrapper(ctx: &UserCtx) -> i64 {
+    let which = ctx[RegType::X0];
+    let who = ctx[RegType::X1];
+    let nice = ctx[RegType::X2] as i64;
+    sys_setpriority(which, who, nice)
+}
+
+pub fn nanosleep_wrapper(ctx: &UserCtx) -> i64 {
+    let req_addr = UserAddr::new(ctx[RegType::X0]);
+    let rem_addr = UserAddr::new(ctx[RegType::X1]);
+    sys_nanosleep(req_addr, rem_addr)
+}
+
+pub fn clock_gettime_wrapper(ctx: &UserCtx) -> i64 {
+    let clock_id = ctx[RegType::X0];
+    let tp_addr = UserAddr::new(ctx[RegType::X1]);
+    sys_clock_gettime(clock_id, tp_addr)
+}
+
+pub fn gettimeofday_wrapper(ctx: &UserCtx) -> i64 {
+    let tv_addr = UserAddr::new(ctx[RegType::X0]);
+    let tz_addr = UserAddr::new(ctx[RegType::X1]);
+    sys_gettimeofday(tv_addr, tz_addr)
+}
@@ -204,6 +204,41 @@ impl fmt::Debug for PageTableEntry {
     }
 }
 
+// Whether EL0 is allowed to write the page
+pub fn is_user_writable(flags: Option<PageTableFlagsField>) -> bool {
+    match flags {
+        Some(flags) => PageTableFlags::AP::EL0_RW_ELX_RW.matches_all(flags.value),
+        None => false,
+    }
+}
+
+// Whether EL0 is allowed to read the page
+pub fn is_user_readable(flags: Option<PageTableFlagsField>) -> bool {
+    match flags {
+        Some(flags) => {
+            PageTableFlags::AP::EL0_RW_ELX_RW.matches_all(flags.value)
+                || PageTableFlags::AP::EL0_OR_ELX_OR.matches_all(flags.value)
+        }
+        None => false,
+    }
+}
+
+// Flags of a COW page.
+// The page is always mapped read-only, WRITABLE_SHARED remembers
+// that a write fault on it should copy the frame.
+pub fn cow_flags(flags: Option<PageTableFlagsField>) -> PageTableFlagsField {
+    let value = match flags {
+        Some(f) if is_user_writable(flags) => {
+            f.value
+                | PageTableFlags::AP::EL0_OR_ELX_OR.value
+                | PageTableFlags::WRITABLE_SHARED::SET.value
+        }
+        Some(f) => f.value | PageTableFlags::READONLY_SHARED::SET.value,
+        None => PageTableFlags::READONLY_SHARED::SET.value,
+    };
+    PageTableFlagsField::new(PGFLAG_MASK, 0, value)
+}
+
 /// The number of entries in a page table.
 const ENTRY_COUNT: usize = 512;
 
@@ -291,20 +326,93 @@ impl PageTable {
                     }
                     va += sz;
                 }
-                FrameObj::Guard(guard_frame) => {
-                    entry.set_flags(flag);
+                // Both are left invalid, the access to them faults.
+                // The lazy one is backed on the fault.
+                FrameObj::Guard(_) => {
+                    entry.clear();
                     va += sz;
                 }
                 FrameObj::Lazy(_) => {
-                    entry.set_flags(flag);
+                    entry.clear();
+                    va += sz;
+                }
+                FrameObj::Cow(data_frame) => {
+                    let pa = data_frame.frame_addr();
+                    let flag = Some(cow_flags(flag));
+                    if layer == 3 {
+                        entry.set_table_page(pa, flag);
+                    } else {
+                        entry.set_huge_page(pa, flag);
+                    }
                     va += sz;
                 }
             }
         }
     }
 
-    pub fn unmap<T: Addr>(&mut self, region: &VmRegion) {
-        todo!()
+    // Only the root_pagetable can use
+    // Free all the tables under the root and clear the root
+    pub fn release(&mut self) {
+        self.release_layer(0);
+    }
+
+    fn release_layer(&mut self, layer: usize) {
+        // Entries of the last layer point to pages
+        if layer < 3 {
+            for _e in self.entries.iter_mut() {
+                if let Some(table) = _e.get_table() {
+                    unsafe { &mut *(phys_to_kernel(table).addr() as *mut PageTable) }
+                        .release_layer(layer + 1);
+                    CURRENT_FRAME_ALLOCATOR
+                        .exclusive_access()
+                        .unsafe_deallo(table);
+                }
+            }
+        }
+        self.zero();
+    }
+
+    // Only the root_pagetable can use
+    pub fn unmap(&mut self, region: &VmRegion) {
+        let mut va = region.start();
+        for _e in region.get_frames() {
+            let layer = match _e.frame_size() {
+                FrameSize::Size4Kb => 3,
+                FrameSize::Size2Mb => 2,
+                FrameSize::Size1Gb => 1,
+            };
+            self.unmap_entry(va, 0, layer);
+            va += _e.frame_size() as u64;
+        }
+    }
+
+    // Clear the entry of va at layer and free the tables left empty.
+    // Return whether this table is empty.
+    fn unmap_entry(&mut self, va: u64, cur: usize, layer: usize) -> bool {
+        let idx = pg_index(va, cur);
+        if cur == layer {
+            // A table may have replaced a huge page
+            if cur < 3 {
+                if let Some(table) = self[idx].get_table() {
+                    unsafe { &mut *(phys_to_kernel(table).addr() as *mut PageTable) }
+                        .release_layer(cur + 1);
+                    CURRENT_FRAME_ALLOCATOR
+                        .exclusive_access()
+                        .unsafe_deallo(table);
+                }
+            }
+            self[idx].clear();
+        } else if let Some(table) = self[idx].get_table() {
+            let empty = unsafe { &mut *(phys_to_kernel(table).addr() as *mut PageTable) }
+                .unmap_entry(va, cur + 1, layer);
+            if empty {
+                CURRENT_FRAME_ALLOCATOR
+                    .exclusive_access()
+                    .unsafe_deallo(table);
+                self[idx].clear();
+            }
+        }
+        self.entries.iter().all(|_e| _e.is_unused())
     }
 }
 
@@ -14,7 +14,7 @@ const REG_NUM: usize = 35;
 const SPSR_EL1_EL0t: usize = 0b0000;
 
 #[repr(C)]
-#[derive(FromBytes)]
+#[derive(FromBytes, Clone)]
 pub struct UserCtx {
     reg: [u64; REG_NUM],
 }
@@ -81,6 +81,11 @@ impl UserCtx {
         self[RegType::ELR_EL1] = pc.addr() as u64;
         println!("user_conctx:init: pc:{:#x}", pc.addr());
     }
+    // main(argc, argv) of the user program
+    pub fn set_args(&mut self, argc: u64, argv: u64) {
+        self[RegType::X0] = argc;
+        self[RegType::X1] = argv;
+    }
 }
 
 pub fn switch_to_vmspace(addr: PhysAddr) {
@@ -97,6 +102,33 @@ pub fn switch_to_vmspace(addr: PhysAddr) {
     }
     //TTBR1_EL1.set_baddr(addr.0 as u64);
 }
+// Flush the stale entries after the page table of the
+// current space is changed
+pub fn flush_tlb() {
+    unsafe {
+        llvm_asm!(
+            "dsb sy
+			 tlbi vmalle1is
+			 dsb sy
+			 isb
+			 "
+            :::: "volatile"
+        );
+    }
+}
+// Flush the entries of va for all ASIDs
+pub fn flush_tlb_va(va: u64) {
+    unsafe {
+        llvm_asm!(
+            "dsb ishst
+			 tlbi vaae1is, $0
+			 dsb ish
+			 isb
+			 "
+            :: "r"(va >> 12) :: "volatile"
+        );
+    }
+}
 pub fn switch_to_user(addr: KernelAddr) {
     unsafe {
         eret_to_user(addr.addr());
@@ -1,3 +1,8 @@
 pub use crate::arch::consts::*;
 pub const ROOT_THREAD_STACK_BASE:u64=0xb000_0000;
 pub const ROOT_THREAD_STACK_SIZE:u64=0x10000;
+// The user stack grows down on faults up to this size
+pub const USER_STACK_MAX_SIZE:u64=0x80_0000;
+// Anonymous mappings are placed above the ELF image and the stack
+pub const USER_MMAP_BASE:u64=0x1_0000_0000;
+pub const USER_SPACE_END:u64=1<<48;
@@ -0,0 +1,129 @@
+use crate::up::UPSafeCell;
+use alloc::{collections::VecDeque, sync::Arc};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use easy_fs::{BlockDevice, EfsError, BLOCK_SZ};
+
+const BLOCK_CACHE_SIZE: usize = 64;
+
+struct CachedBlock {
+    block_id: usize,
+    data: [u8; BLOCK_SZ],
+    dirty: bool,
+}
+
+impl CachedBlock {
+    // The block stays dirty if the write fails
+    fn sync(&mut self, device: &Arc<dyn BlockDevice>) -> Result<(), EfsError> {
+        if self.dirty {
+            device.write_block(self.block_id, &self.data)?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, core::fmt::Debug)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub dirty: usize,
+    pub cached: usize,
+}
+
+// A write back LRU cache in front of a block device.
+// Each block is in its own UPSafeCell, which is a RefCell and not a lock:
+// it only catches overlapping borrows on the single core.
+// The list only orders the blocks.
+pub struct BlockCache {
+    device: Arc<dyn BlockDevice>,
+    // The most recently used block is at the back
+    blocks: UPSafeCell<VecDeque<Arc<UPSafeCell<CachedBlock>>>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl BlockCache {
+    pub fn new(device: Arc<dyn BlockDevice>) -> Self {
+        Self {
+            device,
+            blocks: unsafe { UPSafeCell::new(VecDeque::new()) },
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    // Find the block or make room for it.
+    // A block about to be overwritten as a whole is not read.
+    fn get(&self, block_id: usize, load: bool) -> Result<Arc<UPSafeCell<CachedBlock>>, EfsError> {
+        let mut blocks = self.blocks.exclusive_access();
+        if let Some(pos) = blocks
+            .iter()
+            .position(|_b| _b.exclusive_access().block_id == block_id)
+        {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            let block = blocks.remove(pos).unwrap();
+            blocks.push_back(block.clone());
+            return Ok(block);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        if blocks.len() == BLOCK_CACHE_SIZE {
+            // Evict the least recently used block nobody holds
+            let pos = blocks
+                .iter()
+                .position(|_b| Arc::strong_count(_b) == 1)
+                .expect("Run out of BlockCache!");
+            blocks[pos].exclusive_access().sync(&self.device)?;
+            blocks.remove(pos);
+        }
+        let mut data = [0u8; BLOCK_SZ];
+        if load {
+            self.device.read_block(block_id, &mut data)?;
+        }
+        let block = Arc::new(unsafe {
+            UPSafeCell::new(CachedBlock {
+                block_id,
+                data,
+                dirty: false,
+            })
+        });
+        blocks.push_back(block.clone());
+        Ok(block)
+    }
+
+    // Write every dirty block back to the device,
+    // the last error is returned
+    pub fn sync(&self) -> Result<(), EfsError> {
+        let mut result = Ok(());
+        for _b in self.blocks.exclusive_access().iter() {
+            if let Err(e) = _b.exclusive_access().sync(&self.device) {
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let blocks = self.blocks.exclusive_access();
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            dirty: blocks.iter().filter(|_b| _b.exclusive_access().dirty).count(),
+            cached: blocks.len(),
+        }
+    }
+}
+
+impl BlockDevice for BlockCache {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) -> Result<(), EfsError> {
+        let block = self.get(block_id, true)?;
+        buf.copy_from_slice(&block.exclusive_access().data);
+        Ok(())
+    }
+    fn write_block(&self, block_id: usize, buf: &[u8]) -> Result<(), EfsError> {
+        let block = self.get(block_id, false)?;
+        let mut block = block.exclusive_access();
+        block.data.copy_from_slice(buf);
+        block.dirty = true;
+        Ok(())
+    }
+}
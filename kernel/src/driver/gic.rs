@@ -1,6 +1,6 @@
 use crate::{
     addr_type::KernelAddr,
-    thread::{self, _yield},
+    thread::on_tick,
 };
 
 const GICD_BASE: usize = 0xffff_0000_0800_0000;
@@ -207,9 +207,10 @@ pub fn gicvc2_handler() {
     let irqstat = get32(GICC_IAR);
     let irqnr = irqstat & 0xff_ffff;
     let mut sp = KernelAddr::new(0);
+    let mut tick = false;
     match irqnr {
         30 => {
-            super::timer::timer_irq();
+            tick = super::timer::timer_irq();
         }
         33 => {
             super::pl011::pl011_irq_handler();
@@ -220,8 +221,8 @@ pub fn gicvc2_handler() {
     }
     put32(GICC_EOIR, irqstat);
     put32(GICC_DIR, irqstat);
-    if irqnr == 30 {
-        _yield();
+    if tick {
+        on_tick();
     }
 }
 
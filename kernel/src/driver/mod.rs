@@ -1,14 +1,17 @@
 use device_tree::{DeviceTree,Node, util::SliceRead};
 use virtio_drivers::{DeviceType, VirtIOHeader, VirtIOBlk};
 use core::arch::global_asm;
-use alloc::vec;
 use crate::arch;
 
 pub mod pl011;
 pub mod virtio_impl;
+pub mod virtio_blk;
+pub mod block_cache;
+pub mod rtc;
 pub mod timer;
 pub mod gic;
 pub mod console;
+pub mod tty;
 
 
 pub use pl011::pl01_send;
@@ -63,12 +66,25 @@ fn walk_dt_node(dt: &Node) {
         if compatible == "virtio,mmio" {
             virtio_probe(dt);
         }
+        // The list of compatible strings is separated by NUL
+        if compatible.split('\0').any(|_c| _c == "arm,pl031") {
+            rtc_probe(dt);
+        }
     }
     for child in dt.children.iter() {
         walk_dt_node(child);
     }
 }
 
+fn rtc_probe(node: &Node) {
+    if let Some(reg) = node.prop_raw("reg") {
+        let paddr = reg.as_slice().read_be_u64(0).unwrap();
+        let vaddr = paddr + 0xffff_0000_0000_0000;
+        info!("pl031 rtc at {:#x}", paddr);
+        rtc::rtc_init(vaddr as usize);
+    }
+}
+
 fn virtio_probe(node: &Node) {
     if let Some(reg) = node.prop_raw("reg") {
         let paddr = reg.as_slice().read_be_u64(0).unwrap();
@@ -88,18 +104,14 @@ fn virtio_probe(node: &Node) {
     }
 }
 
+// The config space of a virtio-mmio device starts at 0x100,
+// a block device puts its capacity in sectors first
+const VIRTIO_MMIO_CONFIG: u64 = 0x100;
+
 fn virtio_blk(header: &'static mut VirtIOHeader) {
-    let mut blk = VirtIOBlk::new(header).expect("failed to create blk driver");
-    let mut input = vec![0xffu8; 512];
-    let mut output = vec![0; 512];
-    println!("start to test virtio-blk ");
-    for i in 0..5 {
-        for x in input.iter_mut() {
-            *x = i as u8;
-        }
-        blk.write_block(i, &input).expect("failed to write");
-        blk.read_block(i, &mut output).expect("failed to read");
-        assert_eq!(input, output);
-    }
-    info!("virtio-blk test finished");
-}
\ No newline at end of file
+    let config = header as *mut VirtIOHeader as u64 + VIRTIO_MMIO_CONFIG;
+    let capacity = u64::from_le(unsafe { core::ptr::read_volatile(config as *const u64) });
+    let blk = VirtIOBlk::new(header).expect("failed to create blk driver");
+    virtio_blk::init_block_device(blk, capacity);
+    info!("virtio-blk initialized, capacity={} sectors", capacity);
+}
@@ -0,0 +1,20 @@
+use conquer_once::spin::OnceCell;
+
+// The data register holds the seconds since the epoch
+const RTCDR: usize = 0x000;
+
+// The kernel address of the PL031 found in the device tree
+static RTC_BASE: OnceCell<usize> = OnceCell::uninit();
+
+pub fn rtc_init(base: usize) {
+    if RTC_BASE.try_init_once(|| base).is_err() {
+        warn!("Only the first pl031 is used");
+    }
+}
+
+// Seconds since the epoch, None without a rtc
+pub fn rtc_read() -> Option<u64> {
+    let base = RTC_BASE.try_get().ok()?;
+    let secs = unsafe { core::ptr::read_volatile((base + RTCDR) as *const u32) };
+    Some(secs as u64)
+}
@@ -1,38 +1,119 @@
-use cortex_a::registers::{CNTFRQ_EL0, CNTP_CTL_EL0, CNTP_TVAL_EL0};
+use crate::{thread::wakeup, up::UPSafeCell};
+use alloc::vec::Vec;
+use cortex_a::registers::{CNTFRQ_EL0, CNTPCT_EL0, CNTP_CTL_EL0, CNTP_TVAL_EL0};
 use tock_registers::interfaces::{Readable, Writeable};
 const TICK_MS: u64 = 500;
-static mut cntv_tval: u64 = 0;
+
+// The deadlines are counts of CNTPCT_EL0
+struct TimerQueue {
+    // The counts between two preemption ticks
+    tick_interval: u64,
+    // None while the cpu is idle
+    next_tick: Option<u64>,
+    // (deadline, chanel) sorted by deadline
+    events: Vec<(u64, usize)>,
+}
+
+lazy_static::lazy_static! {
+    static ref TIMER_QUEUE: UPSafeCell<TimerQueue> = unsafe {
+        UPSafeCell::new(TimerQueue {
+            tick_interval: 0,
+            next_tick: None,
+            events: Vec::new(),
+        })
+    };
+}
+
+impl TimerQueue {
+    // Fire the timer at the nearest deadline, stop it if there is none
+    fn program(&self) {
+        let nearest = match (self.next_tick, self.events.first()) {
+            (Some(tick), Some((deadline, _))) => Some(tick.min(*deadline)),
+            (tick, event) => tick.or(event.map(|(deadline, _)| *deadline)),
+        };
+        match nearest {
+            Some(deadline) => {
+                // A far deadline takes several rounds
+                let tval = deadline.saturating_sub(counter()).max(1).min(i32::MAX as u64);
+                CNTP_TVAL_EL0.set(tval);
+                CNTP_CTL_EL0.write(CNTP_CTL_EL0::ENABLE::SET + CNTP_CTL_EL0::IMASK::CLEAR);
+            }
+            None => timer_disable(),
+        }
+    }
+}
 
 pub fn timer_init() {
     let frq = CNTFRQ_EL0.get();
     println!("frequency:{}", frq);
-    let tval = frq * TICK_MS / 1000;
-    unsafe {
-        cntv_tval = tval;
-    }
-    CNTP_TVAL_EL0.set(tval);
-    println!("set timer: {}", CNTP_TVAL_EL0.get());
+    TIMER_QUEUE.exclusive_access().tick_interval = frq * TICK_MS / 1000;
+    println!("set tick: {} ms", TICK_MS);
 }
 
-fn plat_handle_timer_irq() {
-    unsafe {
-        CNTP_TVAL_EL0.set(cntv_tval);
+// Wake up the due events.
+// Return true if the preemption tick is due.
+pub fn timer_irq() -> bool {
+    let now = counter();
+    let mut queue = TIMER_QUEUE.exclusive_access();
+    let due = queue.events.iter().take_while(|(_d, _)| *_d <= now).count();
+    let chans: Vec<usize> = queue.events.drain(..due).map(|(_, _c)| _c).collect();
+    let tick = queue.next_tick.map_or(false, |_t| _t <= now);
+    if tick {
+        queue.next_tick = Some(now + queue.tick_interval);
     }
-    CNTP_CTL_EL0
-        .write(CNTP_CTL_EL0::ENABLE::SET + CNTP_CTL_EL0::IMASK::CLEAR + CNTP_CTL_EL0::IMASK::CLEAR);
+    queue.program();
+    drop(queue);
+    for _c in chans {
+        wakeup(_c);
+    }
+    tick
+}
+
+// Wake up chan once the counter reaches deadline
+pub fn add_timer(deadline: u64, chan: usize) {
+    let mut queue = TIMER_QUEUE.exclusive_access();
+    let pos = queue.events.iter().take_while(|(_d, _)| *_d <= deadline).count();
+    queue.events.insert(pos, (deadline, chan));
+    queue.program();
 }
 
-pub fn timer_irq() {
-    plat_handle_timer_irq();
+// Remove the events of chan which have not fired
+pub fn cancel_timer(chan: usize) {
+    let mut queue = TIMER_QUEUE.exclusive_access();
+    queue.events.retain(|(_, _c)| *_c != chan);
+    queue.program();
 }
 
+// Start the preemption tick
 pub fn timer_enable() {
-    CNTP_CTL_EL0
-        .write(CNTP_CTL_EL0::ENABLE::SET + CNTP_CTL_EL0::IMASK::CLEAR + CNTP_CTL_EL0::IMASK::CLEAR);
+    let mut queue = TIMER_QUEUE.exclusive_access();
+    if queue.next_tick.is_none() {
+        queue.next_tick = Some(counter() + queue.tick_interval);
+    }
+    queue.program();
+}
+
+// Stop the preemption tick, the events still fire
+pub fn tick_disable() {
+    let mut queue = TIMER_QUEUE.exclusive_access();
+    queue.next_tick = None;
+    queue.program();
 }
 
 pub fn timer_disable() {
-    CNTP_CTL_EL0.write(
-        CNTP_CTL_EL0::ENABLE::CLEAR + CNTP_CTL_EL0::IMASK::CLEAR + CNTP_CTL_EL0::IMASK::CLEAR,
-    );
+    CNTP_CTL_EL0.write(CNTP_CTL_EL0::ENABLE::CLEAR + CNTP_CTL_EL0::IMASK::CLEAR);
+}
+
+// The count of the physical counter since boot
+pub fn counter() -> u64 {
+    CNTPCT_EL0.get()
+}
+
+// The counts per second
+pub fn frequency() -> u64 {
+    CNTFRQ_EL0.get()
+}
+
+pub fn counter_to_ms(count: u64) -> u64 {
+    count * 1000 / CNTFRQ_EL0.get()
 }
@@ -0,0 +1,238 @@
+use crate::{
+    process::current_process,
+    thread::{find_process, kill, sleep, wakeup},
+    up::UPSafeCell,
+};
+use alloc::{collections::VecDeque, vec::Vec};
+
+//ctrl+a 1
+//..
+//ctrl+d 4
+//..
+//ctrl+z 26
+const CTRL_C: u8 = 3;
+const CTRL_D: u8 = 4;
+const BACKSPACE: u8 = 8;
+const CTRL_U: u8 = 21;
+const CTRL_W: u8 = 23;
+const DELETE: u8 = 127;
+
+// Local mode flags, same bits as the c_lflag of termios
+pub const ISIG: u32 = 0x1;
+pub const ICANON: u32 = 0x2;
+pub const ECHO: u32 = 0x8;
+
+// ioctl commands, the argument is passed by value
+// TCGETS returns the mode flags, TCSETS replaces them
+// TIOCGPGRP returns the foreground pid, TIOCSPGRP sets it to a live pid
+pub const TCGETS: u64 = 0x5401;
+pub const TCSETS: u64 = 0x5402;
+pub const TIOCGPGRP: u64 = 0x540f;
+pub const TIOCSPGRP: u64 = 0x5410;
+
+const LINE_MAX: usize = 128;
+const INPUT_MAX: usize = 1024;
+// The readers of the tty sleep on this chanel
+const TTY_CHAN: usize = 1;
+
+pub struct Tty {
+    mode: u32,
+    // The line under edit in canonical mode
+    line: Vec<u8>,
+    // The bytes ready for the readers
+    input: VecDeque<u8>,
+    // Set by ctrl+d on an empty line, the next read returns 0
+    eof: bool,
+    // The process interrupted by ctrl+c
+    foreground: Option<usize>,
+}
+
+lazy_static::lazy_static! {
+    static ref TTY: UPSafeCell<Tty> = unsafe { UPSafeCell::new(Tty::new()) };
+}
+
+impl Tty {
+    pub fn new() -> Self {
+        Self {
+            mode: ISIG | ICANON | ECHO,
+            line: Vec::new(),
+            input: VecDeque::new(),
+            eof: false,
+            foreground: None,
+        }
+    }
+
+    fn echo(&self, s: &str) {
+        if self.mode & ECHO != 0 {
+            print!("{}", s);
+        }
+    }
+
+    fn push_input(&mut self, ch: u8) {
+        // Drop the input when nobody reads it
+        if self.input.len() < INPUT_MAX {
+            self.input.push_back(ch);
+        }
+    }
+
+    // Erase the last char of the line, return false if it is empty
+    fn erase(&mut self) -> bool {
+        if self.line.pop().is_some() {
+            self.echo("\x08 \x08");
+            true
+        } else {
+            false
+        }
+    }
+
+    fn erase_word(&mut self) {
+        while self.line.last() == Some(&b' ') {
+            self.erase();
+        }
+        while self.line.last().map_or(false, |_c| *_c != b' ') {
+            self.erase();
+        }
+    }
+
+    fn flush_line(&mut self) {
+        for _c in core::mem::take(&mut self.line) {
+            self.push_input(_c);
+        }
+    }
+
+    // Handle a received char.
+    // Return the pid to interrupt when ctrl+c is pressed.
+    fn receive(&mut self, ch: u8) -> Option<usize> {
+        if self.mode & ISIG != 0 && ch == CTRL_C {
+            self.line.clear();
+            self.echo("^C\n");
+            return self.foreground;
+        }
+        if self.mode & ICANON == 0 {
+            self.push_input(ch);
+            if ch.is_ascii_graphic() || ch == b' ' {
+                self.echo(core::str::from_utf8(&[ch]).unwrap());
+            }
+            return None;
+        }
+        match ch {
+            b'\r' | b'\n' => {
+                self.echo("\n");
+                self.flush_line();
+                self.push_input(b'\n');
+            }
+            BACKSPACE | DELETE => {
+                self.erase();
+            }
+            CTRL_U => while self.erase() {},
+            CTRL_W => self.erase_word(),
+            CTRL_D => {
+                if self.line.is_empty() {
+                    self.eof = true;
+                } else {
+                    self.flush_line();
+                }
+            }
+            _ if ch.is_ascii_graphic() || ch == b' ' => {
+                if self.line.len() < LINE_MAX {
+                    self.line.push(ch);
+                    self.echo(core::str::from_utf8(&[ch]).unwrap());
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn readable(&self) -> bool {
+        !self.input.is_empty() || self.eof
+    }
+
+    // Read the ready bytes, in canonical mode a read stops at the end of a line
+    fn read(&mut self, buf: &mut [u8]) -> usize {
+        if self.input.is_empty() && self.eof {
+            self.eof = false;
+            return 0;
+        }
+        let mut len = 0;
+        while len < buf.len() {
+            match self.input.pop_front() {
+                Some(ch) => {
+                    buf[len] = ch;
+                    len += 1;
+                    if self.mode & ICANON != 0 && ch == b'\n' {
+                        break;
+                    }
+                }
+                None => break,
+            }
+        }
+        len
+    }
+}
+
+pub fn tty_receive(ch: u8) {
+    let interrupted = TTY.exclusive_access().receive(ch);
+    if let Some(pid) = interrupted {
+        kill(pid);
+    }
+    if TTY.exclusive_access().readable() {
+        wakeup(TTY_CHAN);
+    }
+}
+
+// A new process takes the foreground from its parent,
+// the first process gets it if nobody holds it
+pub fn tty_on_spawn(parent: Option<usize>, pid: usize) {
+    let mut tty = TTY.exclusive_access();
+    if tty.foreground == parent {
+        tty.foreground = Some(pid);
+    }
+}
+
+// The foreground goes back to the parent of an exiting process
+pub fn tty_on_exit(pid: usize, parent: Option<usize>) {
+    let mut tty = TTY.exclusive_access();
+    if tty.foreground == Some(pid) {
+        tty.foreground = parent;
+    }
+}
+
+// Block until the tty is readable.
+// Return None if the reader is killed while waiting.
+pub fn tty_read(buf: &mut [u8]) -> Option<usize> {
+    let process = current_process();
+    while !TTY.exclusive_access().readable() {
+        if process.exclusive_access().is_killed() {
+            return None;
+        }
+        sleep(TTY_CHAN);
+    }
+    Some(TTY.exclusive_access().read(buf))
+}
+
+pub fn tty_ioctl(cmd: u64, arg: u64) -> i64 {
+    let mut tty = TTY.exclusive_access();
+    match cmd {
+        TCGETS => tty.mode as i64,
+        TCSETS => {
+            tty.mode = arg as u32 & (ISIG | ICANON | ECHO);
+            // The partial line is kept for the readers when leaving canonical mode
+            if tty.mode & ICANON == 0 {
+                tty.flush_line();
+            }
+            0
+        }
+        TIOCGPGRP => tty.foreground.map_or(-1, |_p| _p as i64),
+        TIOCSPGRP => {
+            let live = find_process(arg as usize)
+                .map_or(false, |_p| !_p.exclusive_access().is_zombie());
+            if !live {
+                return -1;
+            }
+            tty.foreground = Some(arg as usize);
+            0
+        }
+        _ => -1,
+    }
+}
@@ -0,0 +1,45 @@
+use crate::up::UPSafeCell;
+use alloc::sync::Arc;
+use conquer_once::spin::OnceCell;
+use easy_fs::{BlockDevice, EfsError, BLOCK_SZ};
+use virtio_drivers::VirtIOBlk;
+
+// The disk found in the device tree, if any
+pub static BLOCK_DEVICE: OnceCell<Arc<VirtIOBlock>> = OnceCell::uninit();
+
+pub struct VirtIOBlock {
+    blk: UPSafeCell<VirtIOBlk<'static>>,
+    // The capacity in sectors of 512 bytes
+    capacity: u64,
+}
+
+impl VirtIOBlock {
+    pub fn num_blocks(&self) -> usize {
+        self.capacity as usize * 512 / BLOCK_SZ
+    }
+}
+
+impl BlockDevice for VirtIOBlock {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) -> Result<(), EfsError> {
+        self.blk
+            .exclusive_access()
+            .read_block(block_id, buf)
+            .map_err(|_| EfsError::Io)
+    }
+    fn write_block(&self, block_id: usize, buf: &[u8]) -> Result<(), EfsError> {
+        self.blk
+            .exclusive_access()
+            .write_block(block_id, buf)
+            .map_err(|_| EfsError::Io)
+    }
+}
+
+pub fn init_block_device(blk: VirtIOBlk<'static>, capacity: u64) {
+    let device = Arc::new(VirtIOBlock {
+        blk: unsafe { UPSafeCell::new(blk) },
+        capacity,
+    });
+    if BLOCK_DEVICE.try_init_once(|| device).is_err() {
+        warn!("Only the first virtio-blk is used");
+    }
+}
@@ -2,7 +2,7 @@ use crate::{
     addr_type::{phys_to_kernel, Addr, PhysAddr},
     frame_allocator::{FrameAllocator, CURRENT_FRAME_ALLOCATOR},
 };
-use alloc::slice;
+use alloc::{slice, sync::Arc};
 use core::mem;
 use zerocopy::FromBytes;
 
@@ -23,6 +23,8 @@ pub enum FrameObj {
     Data(DataFrame),
     Guard(GuardFrame),
     Lazy(LazyFrame),
+    // Shared by the spaces of forked processes until one of them writes it
+    Cow(Arc<DataFrame>),
 }
 
 impl FrameObj {
@@ -31,6 +33,7 @@ impl FrameObj {
             FrameObj::Data(data) => data.frame_size(),
             FrameObj::Guard(guard) => guard.frame_size(),
             FrameObj::Lazy(lazy) => lazy.frame_size(),
+            FrameObj::Cow(data) => data.frame_size(),
         }
     }
 }
@@ -98,6 +101,10 @@ impl DataFrame {
         Ok(slc)
     }
 
+    pub fn zero(&self) {
+        self.as_slice_mut::<u8>(0, self.size as u64).unwrap().fill(0);
+    }
+
     pub fn as_type<T: FromBytes>(&self, offset: u64) -> Result<&T, &'static str> {
         let size_in_byte = mem::size_of::<T>() as u64;
         let frame_size = self.size as u64;
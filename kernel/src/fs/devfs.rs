@@ -0,0 +1,213 @@
+use super::{
+    vfs::{DirEntry, FileSystem, Inode, InodeType, Metadata},
+    FsError,
+};
+use crate::{
+    driver::{block_cache::BlockCache, console, timer, tty},
+    thread::CURRENT_CPU,
+};
+use alloc::{
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
+use easy_fs::{BlockDevice, BLOCK_SZ};
+
+// The devices seen as files, the directory is fixed once mounted
+pub struct DevFs {
+    root: Arc<DevDir>,
+}
+
+impl DevFs {
+    // The disk is shared with the filesystem on it through the cache
+    pub fn new(disk: Option<(Arc<BlockCache>, usize)>) -> Self {
+        let mut devices: Vec<(String, Arc<dyn Inode>)> = vec![
+            ("console".to_string(), Arc::new(ConsoleDev)),
+            ("null".to_string(), Arc::new(NullDev)),
+            ("zero".to_string(), Arc::new(ZeroDev)),
+            ("cpustat".to_string(), Arc::new(CpuStatDev)),
+        ];
+        if let Some((cache, num_blocks)) = disk {
+            devices.push(("vda".to_string(), Arc::new(BlockDev { cache, num_blocks })));
+        }
+        Self {
+            root: Arc::new(DevDir { devices }),
+        }
+    }
+}
+
+impl FileSystem for DevFs {
+    fn root(&self) -> Arc<dyn Inode> {
+        self.root.clone()
+    }
+}
+
+struct DevDir {
+    devices: Vec<(String, Arc<dyn Inode>)>,
+}
+
+impl Inode for DevDir {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            ino: 1,
+            _type: InodeType::Dir,
+            size: self.devices.len(),
+        }
+    }
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>, FsError> {
+        self.devices
+            .iter()
+            .find(|(_name, _)| _name == name)
+            .map(|(_, _inode)| _inode.clone())
+            .ok_or(FsError::NotFound)
+    }
+    fn create(&self, _name: &str, _type: InodeType) -> Result<Arc<dyn Inode>, FsError> {
+        Err(FsError::NotSupported)
+    }
+    fn list(&self) -> Result<Vec<DirEntry>, FsError> {
+        Ok(self
+            .devices
+            .iter()
+            .map(|(_name, _inode)| DirEntry {
+                name: _name.clone(),
+                _type: _inode.metadata()._type,
+            })
+            .collect())
+    }
+}
+
+// The console seen through the tty line discipline
+struct ConsoleDev;
+
+impl Inode for ConsoleDev {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            ino: 2,
+            _type: InodeType::CharDevice,
+            size: 0,
+        }
+    }
+    fn read_at(&self, _offset: usize, buf: &mut [u8]) -> Result<usize, FsError> {
+        console::console_read(buf).ok_or(FsError::Interrupted)
+    }
+    fn write_at(&self, _offset: usize, buf: &[u8]) -> Result<usize, FsError> {
+        Ok(console::console_write(buf))
+    }
+    fn ioctl(&self, cmd: u64, arg: u64) -> Result<i64, FsError> {
+        Ok(tty::tty_ioctl(cmd, arg))
+    }
+}
+
+// Discard the writes, a read is always at the end
+struct NullDev;
+
+impl Inode for NullDev {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            ino: 3,
+            _type: InodeType::CharDevice,
+            size: 0,
+        }
+    }
+    fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> Result<usize, FsError> {
+        Ok(0)
+    }
+    fn write_at(&self, _offset: usize, buf: &[u8]) -> Result<usize, FsError> {
+        Ok(buf.len())
+    }
+}
+
+// Discard the writes, a read fills the buffer with zeros
+struct ZeroDev;
+
+impl Inode for ZeroDev {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            ino: 4,
+            _type: InodeType::CharDevice,
+            size: 0,
+        }
+    }
+    fn read_at(&self, _offset: usize, buf: &mut [u8]) -> Result<usize, FsError> {
+        buf.fill(0);
+        Ok(buf.len())
+    }
+    fn write_at(&self, _offset: usize, buf: &[u8]) -> Result<usize, FsError> {
+        Ok(buf.len())
+    }
+}
+
+// The uptime and the idle time of the cpu as text
+struct CpuStatDev;
+
+impl Inode for CpuStatDev {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            ino: 6,
+            _type: InodeType::CharDevice,
+            size: 0,
+        }
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize, FsError> {
+        let idle_ms = CURRENT_CPU
+            .try_get()
+            .map_or(0, |_c| _c.exclusive_access().idle_ms());
+        let stat = format!(
+            "uptime_ms {}\nidle_ms {}\n",
+            timer::counter_to_ms(timer::counter()),
+            idle_ms
+        );
+        let stat = stat.as_bytes();
+        if offset >= stat.len() {
+            return Ok(0);
+        }
+        let len = core::cmp::min(buf.len(), stat.len() - offset);
+        buf[..len].copy_from_slice(&stat[offset..offset + len]);
+        Ok(len)
+    }
+    fn read_only(&self) -> bool {
+        true
+    }
+}
+
+// The raw disk addressed by bytes, read-only.
+// The easy-fs on it is mounted at / and a raw write
+// would corrupt it behind the back of the filesystem.
+struct BlockDev {
+    cache: Arc<BlockCache>,
+    num_blocks: usize,
+}
+
+impl BlockDev {
+    fn size(&self) -> usize {
+        self.num_blocks * BLOCK_SZ
+    }
+}
+
+impl Inode for BlockDev {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            ino: 5,
+            _type: InodeType::BlockDevice,
+            size: self.size(),
+        }
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize, FsError> {
+        let end = core::cmp::min(offset.saturating_add(buf.len()), self.size());
+        let mut block = [0u8; BLOCK_SZ];
+        let mut pos = offset;
+        while pos < end {
+            let inner = pos % BLOCK_SZ;
+            let len = core::cmp::min(BLOCK_SZ - inner, end - pos);
+            self.cache.read_block(pos / BLOCK_SZ, &mut block)?;
+            buf[pos - offset..pos - offset + len].copy_from_slice(&block[inner..inner + len]);
+            pos += len;
+        }
+        Ok(pos.saturating_sub(offset))
+    }
+    fn read_only(&self) -> bool {
+        true
+    }
+}
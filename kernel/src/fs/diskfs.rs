@@ -0,0 +1,120 @@
+use super::{
+    vfs::{DirEntry, FileSystem, Inode, InodeType, Metadata},
+    FsError,
+};
+use crate::driver::block_cache::BlockCache;
+use alloc::{sync::Arc, vec::Vec};
+use easy_fs::{DiskInodeType, EasyFileSystem};
+
+// An easy-fs on a block device, the blocks go through a cache
+pub struct DiskFs {
+    root: Arc<easy_fs::Inode>,
+    cache: Arc<BlockCache>,
+}
+
+impl DiskFs {
+    // Return None if the device holds no easy-fs or can't be read
+    pub fn open(cache: Arc<BlockCache>) -> Option<Self> {
+        let efs = EasyFileSystem::open(cache.clone()).ok()?;
+        Some(Self {
+            root: Arc::new(EasyFileSystem::root_inode(&efs)),
+            cache,
+        })
+    }
+}
+
+impl FileSystem for DiskFs {
+    fn root(&self) -> Arc<dyn Inode> {
+        Arc::new(DiskInode::new(self.root.clone(), InodeType::Dir))
+    }
+    fn sync(&self) -> Result<(), FsError> {
+        self.cache.sync()?;
+        debug!("block cache after sync {:?}", self.cache.stats());
+        Ok(())
+    }
+}
+
+// The type of an inode never changes, it is read once
+struct DiskInode {
+    inode: Arc<easy_fs::Inode>,
+    _type: InodeType,
+}
+
+fn inode_type(_type: DiskInodeType) -> InodeType {
+    match _type {
+        DiskInodeType::File => InodeType::File,
+        DiskInodeType::Directory => InodeType::Dir,
+    }
+}
+
+impl DiskInode {
+    fn new(inode: Arc<easy_fs::Inode>, _type: InodeType) -> Self {
+        Self { inode, _type }
+    }
+    fn open(inode: Arc<easy_fs::Inode>) -> Result<Self, FsError> {
+        let _type = inode_type(inode.get_type()?);
+        Ok(Self::new(inode, _type))
+    }
+}
+
+impl Inode for DiskInode {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            ino: self.inode.inode_id() as usize,
+            _type: self._type,
+            // An unreadable inode looks empty, reading it reports the error
+            size: self.inode.size().unwrap_or(0),
+        }
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize, FsError> {
+        Ok(self.inode.read_at(offset, buf)?)
+    }
+    fn write_at(&self, offset: usize, buf: &[u8]) -> Result<usize, FsError> {
+        Ok(self.inode.write_at(offset, buf)?)
+    }
+    // Only a file can be emptied
+    fn truncate(&self, size: usize) -> Result<(), FsError> {
+        if size != 0 {
+            return Err(FsError::NotSupported);
+        }
+        Ok(self.inode.clear()?)
+    }
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>, FsError> {
+        if self._type != InodeType::Dir {
+            return Err(FsError::NotDir);
+        }
+        match self.inode.find(name)? {
+            Some(inode) => Ok(Arc::new(DiskInode::open(inode)?)),
+            None => Err(FsError::NotFound),
+        }
+    }
+    fn create(&self, name: &str, _type: InodeType) -> Result<Arc<dyn Inode>, FsError> {
+        let disk_type = match _type {
+            InodeType::File => DiskInodeType::File,
+            InodeType::Dir => DiskInodeType::Directory,
+            _ => return Err(FsError::NotSupported),
+        };
+        match self.inode.create(name, disk_type)? {
+            Some(inode) => Ok(Arc::new(DiskInode::new(inode, _type))),
+            None => Err(FsError::NotSupported),
+        }
+    }
+    // An entry that can't be read is left out
+    fn list(&self) -> Result<Vec<DirEntry>, FsError> {
+        if self._type != InodeType::Dir {
+            return Err(FsError::NotDir);
+        }
+        Ok(self
+            .inode
+            .ls()?
+            .into_iter()
+            .filter_map(|_name| {
+                let inode = self.inode.find(&_name).ok()??;
+                Some(DirEntry {
+                    name: _name,
+                    _type: inode_type(inode.get_type().ok()?),
+                })
+            })
+            .collect())
+    }
+}
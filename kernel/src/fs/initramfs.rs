@@ -0,0 +1,98 @@
+use super::{
+    vfs::{DirEntry, FileSystem, Inode, InodeType, Metadata},
+    FsError,
+};
+use crate::loader;
+use alloc::{string::ToString, sync::Arc, vec::Vec};
+
+// The apps linked into the kernel, read only.
+// The data is read in place, nothing is copied at mount.
+pub struct InitRamFs {
+    root: Arc<AppDir>,
+}
+
+impl InitRamFs {
+    pub fn new() -> Self {
+        let apps = (0..loader::get_num_app())
+            .map(|_i| {
+                Arc::new(AppInode {
+                    ino: _i + 2,
+                    name: loader::get_app_name(_i),
+                    data: loader::get_app_data(_i),
+                })
+            })
+            .collect();
+        Self {
+            root: Arc::new(AppDir { apps }),
+        }
+    }
+}
+
+impl FileSystem for InitRamFs {
+    fn root(&self) -> Arc<dyn Inode> {
+        self.root.clone()
+    }
+}
+
+struct AppDir {
+    apps: Vec<Arc<AppInode>>,
+}
+
+impl Inode for AppDir {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            ino: 1,
+            _type: InodeType::Dir,
+            size: self.apps.len(),
+        }
+    }
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>, FsError> {
+        match self.apps.iter().find(|_a| _a.name == name) {
+            Some(app) => Ok(app.clone()),
+            None => Err(FsError::NotFound),
+        }
+    }
+    fn create(&self, _name: &str, _type: InodeType) -> Result<Arc<dyn Inode>, FsError> {
+        Err(FsError::ReadOnly)
+    }
+    fn list(&self) -> Result<Vec<DirEntry>, FsError> {
+        Ok(self
+            .apps
+            .iter()
+            .map(|_a| DirEntry {
+                name: _a.name.to_string(),
+                _type: InodeType::File,
+            })
+            .collect())
+    }
+    fn read_only(&self) -> bool {
+        true
+    }
+}
+
+struct AppInode {
+    ino: usize,
+    name: &'static str,
+    data: &'static [u8],
+}
+
+impl Inode for AppInode {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            ino: self.ino,
+            _type: InodeType::File,
+            size: self.data.len(),
+        }
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize, FsError> {
+        if offset >= self.data.len() {
+            return Ok(0);
+        }
+        let len = core::cmp::min(buf.len(), self.data.len() - offset);
+        buf[..len].copy_from_slice(&self.data[offset..offset + len]);
+        Ok(len)
+    }
+    fn read_only(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,66 @@
+use super::{push_dirent, vfs::Dentry, File, FsError};
+use crate::up::UPSafeCell;
+
+// A file opened on an inode, the offset moves with every read and write
+pub struct InodeFile {
+    readable: bool,
+    writable: bool,
+    dentry: Dentry,
+    offset: UPSafeCell<usize>,
+}
+
+impl InodeFile {
+    pub fn new(dentry: Dentry, readable: bool, writable: bool) -> Self {
+        Self {
+            readable,
+            writable,
+            dentry,
+            offset: unsafe { UPSafeCell::new(0) },
+        }
+    }
+}
+
+impl File for InodeFile {
+    fn readable(&self) -> bool {
+        self.readable
+    }
+    fn writable(&self) -> bool {
+        self.writable
+    }
+    // The offset is not borrowed across the inode operation,
+    // a device inode may block in it
+    fn read(&self, buf: &mut [u8]) -> Result<usize, FsError> {
+        let offset = *self.offset.exclusive_access();
+        let len = self.dentry.inode.read_at(offset, buf)?;
+        *self.offset.exclusive_access() += len;
+        Ok(len)
+    }
+    fn write(&self, buf: &[u8]) -> Result<usize, FsError> {
+        let offset = *self.offset.exclusive_access();
+        let len = self.dentry.inode.write_at(offset, buf)?;
+        *self.offset.exclusive_access() += len;
+        Ok(len)
+    }
+    fn ioctl(&self, cmd: u64, arg: u64) -> Result<i64, FsError> {
+        self.dentry.inode.ioctl(cmd, arg)
+    }
+    // The offset of a directory counts the entries read
+    fn getdents(&self, buf: &mut [u8]) -> Result<usize, FsError> {
+        let entries = self.dentry.inode.list()?;
+        let mut offset = self.offset.exclusive_access();
+        let mut len = 0;
+        for _e in entries.iter().skip(*offset) {
+            let ino = match self.dentry.inode.lookup(&_e.name) {
+                Ok(inode) => inode.metadata().ino,
+                Err(_) => 0,
+            };
+            match push_dirent(&mut buf[len..], ino, *offset + 1, _e._type, &_e.name) {
+                Some(reclen) => len += reclen,
+                None if len == 0 => return Err(FsError::NoSpace),
+                None => break,
+            }
+            *offset += 1;
+        }
+        Ok(len)
+    }
+}
@@ -0,0 +1,193 @@
+use crate::driver::{block_cache::BlockCache, virtio_blk::BLOCK_DEVICE};
+use alloc::{sync::Arc, vec, vec::Vec};
+use easy_fs::EfsError;
+
+mod devfs;
+mod diskfs;
+mod initramfs;
+mod inode_file;
+mod pipe;
+mod rootfs;
+mod tmpfs;
+pub mod vfs;
+pub use inode_file::InodeFile;
+pub use pipe::make_pipe;
+pub use vfs::{Dentry, FileSystem, Inode, InodeType};
+
+#[derive(core::fmt::Debug)]
+pub enum FsError {
+    NotFound,
+    NotDir,
+    IsDir,
+    Exists,
+    // The file does not support the operation
+    NotSupported,
+    // The caller is killed while blocking
+    Interrupted,
+    // No frame is left for the data
+    NoSpace,
+    // Write to a pipe without readers
+    BrokenPipe,
+    // The disk failed or holds inconsistent data
+    Io,
+    // Write to a read-only file or filesystem
+    ReadOnly,
+}
+
+impl From<EfsError> for FsError {
+    fn from(_: EfsError) -> Self {
+        FsError::Io
+    }
+}
+
+bitflags! {
+    pub struct OpenFlags: u32 {
+        const RDONLY = 0;
+        const WRONLY = 1 << 0;
+        const RDWR = 1 << 1;
+        const CREATE = 1 << 6;
+        const TRUNC = 1 << 9;
+    }
+}
+
+impl OpenFlags {
+    // Return (readable, writable)
+    pub fn read_write(&self) -> (bool, bool) {
+        if self.contains(Self::RDWR) {
+            (true, true)
+        } else if self.contains(Self::WRONLY) {
+            (false, true)
+        } else {
+            (true, false)
+        }
+    }
+}
+
+// An opened file shared by the fd tables
+pub trait File: Send + Sync {
+    fn readable(&self) -> bool;
+    fn writable(&self) -> bool;
+    fn read(&self, buf: &mut [u8]) -> Result<usize, FsError>;
+    fn write(&self, buf: &[u8]) -> Result<usize, FsError>;
+    fn ioctl(&self, _cmd: u64, _arg: u64) -> Result<i64, FsError> {
+        Err(FsError::NotSupported)
+    }
+    // Fill buf with the next entries of a directory
+    fn getdents(&self, _buf: &mut [u8]) -> Result<usize, FsError> {
+        Err(FsError::NotDir)
+    }
+}
+
+// The layout of linux_dirent64, the name follows the header
+// and the record is padded to 8 bytes
+const DIRENT_HEADER: usize = 19;
+
+fn dirent_type(_type: InodeType) -> u8 {
+    match _type {
+        InodeType::CharDevice => 2,
+        InodeType::Dir => 4,
+        InodeType::BlockDevice => 6,
+        InodeType::File => 8,
+    }
+}
+
+// Put a record at the start of buf, return its length
+// or None if buf is too small
+fn push_dirent(buf: &mut [u8], ino: usize, next: usize, _type: InodeType, name: &str) -> Option<usize> {
+    let reclen = (DIRENT_HEADER + name.len() + 1 + 7) & !7;
+    if reclen > buf.len() {
+        return None;
+    }
+    let record = &mut buf[..reclen];
+    record.fill(0);
+    record[0..8].copy_from_slice(&(ino as u64).to_ne_bytes());
+    record[8..16].copy_from_slice(&(next as i64).to_ne_bytes());
+    record[16..18].copy_from_slice(&(reclen as u16).to_ne_bytes());
+    record[18] = dirent_type(_type);
+    record[DIRENT_HEADER..DIRENT_HEADER + name.len()].copy_from_slice(name.as_bytes());
+    Some(reclen)
+}
+
+// Mount the disk as the root filesystem,
+// fall back to an empty one without a formatted disk.
+// Then mount the apps at /bin, a tmpfs at /tmp and the devices at /dev.
+pub fn init() {
+    // The diskfs and /dev/vda share the cache of the disk
+    let disk = BLOCK_DEVICE
+        .try_get()
+        .ok()
+        .map(|_d| (Arc::new(BlockCache::new(_d.clone())), _d.num_blocks()));
+    let diskfs = disk
+        .as_ref()
+        .and_then(|(_cache, _)| diskfs::DiskFs::open(_cache.clone()));
+    match diskfs {
+        Some(diskfs) => {
+            vfs::mount("/", Arc::new(diskfs)).unwrap();
+            info!("Mount the easy-fs on virtio-blk at /");
+        }
+        None => {
+            vfs::mount("/", Arc::new(rootfs::RootFs::new())).unwrap();
+            warn!("No easy-fs found, / is an empty rootfs");
+        }
+    }
+    mount_at("/bin", Arc::new(initramfs::InitRamFs::new()));
+    mount_at("/tmp", Arc::new(tmpfs::TmpFs::new()));
+    mount_at("/dev", Arc::new(devfs::DevFs::new(disk)));
+}
+
+// Create the mount point if it is missing
+fn mount_at(path: &str, fs: Arc<dyn FileSystem>) {
+    if let Err(FsError::NotFound) = vfs::lookup(path) {
+        if let Err(e) = vfs::create(path, InodeType::Dir) {
+            warn!("Fail to create the mount point {}: {:?}", path, e);
+            return;
+        }
+    }
+    match vfs::mount(path, fs) {
+        Ok(()) => info!("Mount {}", path),
+        Err(e) => warn!("Fail to mount {}: {:?}", path, e),
+    }
+}
+
+pub fn open_file(path: &str, flags: OpenFlags) -> Result<Arc<dyn File>, FsError> {
+    let (readable, writable) = flags.read_write();
+    let dentry = match vfs::lookup(path) {
+        Err(FsError::NotFound) if flags.contains(OpenFlags::CREATE) => {
+            vfs::create(path, InodeType::File)?
+        }
+        r => r?,
+    };
+    let _type = dentry.inode.metadata()._type;
+    if _type == InodeType::Dir && writable {
+        return Err(FsError::IsDir);
+    }
+    if writable && dentry.inode.read_only() {
+        return Err(FsError::ReadOnly);
+    }
+    // A device keeps its content
+    if flags.contains(OpenFlags::TRUNC) && writable && _type == InodeType::File {
+        dentry.inode.truncate(0)?;
+    }
+    Ok(Arc::new(InodeFile::new(dentry, readable, writable)))
+}
+
+// Read the whole content of a regular file
+pub fn read_file(path: &str) -> Result<Vec<u8>, FsError> {
+    let inode = vfs::lookup(path)?.inode;
+    let metadata = inode.metadata();
+    match metadata._type {
+        InodeType::File => {}
+        InodeType::Dir => return Err(FsError::IsDir),
+        _ => return Err(FsError::NotSupported),
+    }
+    let mut data = vec![0u8; metadata.size];
+    let mut pos = 0;
+    while pos < data.len() {
+        match inode.read_at(pos, &mut data[pos..])? {
+            0 => break,
+            len => pos += len,
+        }
+    }
+    data.truncate(pos);
+    Ok(data)
+}
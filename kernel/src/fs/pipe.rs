@@ -0,0 +1,144 @@
+use super::{File, FsError};
+use crate::{
+    process::current_process,
+    thread::{sleep, wakeup},
+    up::UPSafeCell,
+};
+use alloc::sync::Arc;
+
+const PIPE_SIZE: usize = 4096;
+
+// The bytes in flight between the two ends
+struct PipeRing {
+    buf: [u8; PIPE_SIZE],
+    head: usize,
+    len: usize,
+    readers: usize,
+    writers: usize,
+}
+
+impl PipeRing {
+    fn new() -> Self {
+        Self {
+            buf: [0; PIPE_SIZE],
+            head: 0,
+            len: 0,
+            readers: 1,
+            writers: 1,
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> usize {
+        let len = core::cmp::min(buf.len(), self.len);
+        for _b in buf[..len].iter_mut() {
+            *_b = self.buf[self.head];
+            self.head = (self.head + 1) % PIPE_SIZE;
+        }
+        self.len -= len;
+        len
+    }
+
+    fn write(&mut self, buf: &[u8]) -> usize {
+        let len = core::cmp::min(buf.len(), PIPE_SIZE - self.len);
+        for _b in buf[..len].iter() {
+            self.buf[(self.head + self.len) % PIPE_SIZE] = *_b;
+            self.len += 1;
+        }
+        len
+    }
+}
+
+// One end of a pipe.
+// The readers sleep on the address of the ring, the writers on the next byte.
+pub struct Pipe {
+    writable: bool,
+    ring: Arc<UPSafeCell<PipeRing>>,
+}
+
+// Return the read end and the write end
+pub fn make_pipe() -> (Arc<Pipe>, Arc<Pipe>) {
+    let ring = Arc::new(unsafe { UPSafeCell::new(PipeRing::new()) });
+    let read_end = Arc::new(Pipe {
+        writable: false,
+        ring: ring.clone(),
+    });
+    let write_end = Arc::new(Pipe {
+        writable: true,
+        ring,
+    });
+    (read_end, write_end)
+}
+
+impl Pipe {
+    fn read_chan(&self) -> usize {
+        Arc::as_ptr(&self.ring) as usize
+    }
+    fn write_chan(&self) -> usize {
+        self.read_chan() + 1
+    }
+}
+
+fn is_killed() -> bool {
+    current_process().exclusive_access().is_killed()
+}
+
+impl File for Pipe {
+    fn readable(&self) -> bool {
+        !self.writable
+    }
+    fn writable(&self) -> bool {
+        self.writable
+    }
+    // Block until some bytes come, return 0 once every write end is closed
+    fn read(&self, buf: &mut [u8]) -> Result<usize, FsError> {
+        loop {
+            let mut ring = self.ring.exclusive_access();
+            if ring.len > 0 || ring.writers == 0 || buf.is_empty() {
+                let len = ring.read(buf);
+                drop(ring);
+                wakeup(self.write_chan());
+                return Ok(len);
+            }
+            drop(ring);
+            if is_killed() {
+                return Err(FsError::Interrupted);
+            }
+            sleep(self.read_chan());
+        }
+    }
+    // Block until there is room, fail once every read end is closed
+    fn write(&self, buf: &[u8]) -> Result<usize, FsError> {
+        loop {
+            let mut ring = self.ring.exclusive_access();
+            if ring.readers == 0 {
+                return Err(FsError::BrokenPipe);
+            }
+            if ring.len < PIPE_SIZE || buf.is_empty() {
+                let len = ring.write(buf);
+                drop(ring);
+                wakeup(self.read_chan());
+                return Ok(len);
+            }
+            drop(ring);
+            if is_killed() {
+                return Err(FsError::Interrupted);
+            }
+            sleep(self.write_chan());
+        }
+    }
+}
+
+// The last fd of an end is closed
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        let mut ring = self.ring.exclusive_access();
+        if self.writable {
+            ring.writers -= 1;
+        } else {
+            ring.readers -= 1;
+        }
+        drop(ring);
+        wakeup(self.read_chan());
+        wakeup(self.write_chan());
+    }
+}
@@ -0,0 +1,85 @@
+use super::{
+    vfs::{DirEntry, FileSystem, Inode, InodeType, Metadata},
+    FsError,
+};
+use crate::up::UPSafeCell;
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_INO: AtomicUsize = AtomicUsize::new(1);
+
+// The filesystem at / before a disk is mounted.
+// It only holds directories for the other filesystems to mount on.
+pub struct RootFs {
+    root: Arc<RootDir>,
+}
+
+impl RootFs {
+    pub fn new() -> Self {
+        Self {
+            root: Arc::new(RootDir::new()),
+        }
+    }
+}
+
+impl FileSystem for RootFs {
+    fn root(&self) -> Arc<dyn Inode> {
+        self.root.clone()
+    }
+}
+
+struct RootDir {
+    ino: usize,
+    children: UPSafeCell<BTreeMap<String, Arc<RootDir>>>,
+}
+
+impl RootDir {
+    fn new() -> Self {
+        Self {
+            ino: NEXT_INO.fetch_add(1, Ordering::Relaxed),
+            children: unsafe { UPSafeCell::new(BTreeMap::new()) },
+        }
+    }
+}
+
+impl Inode for RootDir {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            ino: self.ino,
+            _type: InodeType::Dir,
+            size: 0,
+        }
+    }
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>, FsError> {
+        match self.children.exclusive_access().get(name) {
+            Some(dir) => Ok(dir.clone()),
+            None => Err(FsError::NotFound),
+        }
+    }
+    fn create(&self, name: &str, _type: InodeType) -> Result<Arc<dyn Inode>, FsError> {
+        if _type != InodeType::Dir {
+            return Err(FsError::NotSupported);
+        }
+        let dir = Arc::new(RootDir::new());
+        self.children
+            .exclusive_access()
+            .insert(name.to_string(), dir.clone());
+        Ok(dir)
+    }
+    fn list(&self) -> Result<Vec<DirEntry>, FsError> {
+        Ok(self
+            .children
+            .exclusive_access()
+            .keys()
+            .map(|_name| DirEntry {
+                name: _name.clone(),
+                _type: InodeType::Dir,
+            })
+            .collect())
+    }
+}
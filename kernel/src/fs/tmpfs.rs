@@ -0,0 +1,195 @@
+use super::{
+    vfs::{DirEntry, FileSystem, Inode, InodeType, Metadata},
+    FsError,
+};
+use crate::{
+    frame::{DataFrame, FrameSize},
+    frame_allocator::{FrameAllocator, CURRENT_FRAME_ALLOCATOR},
+    up::UPSafeCell,
+};
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+const FRAME_SIZE: usize = FrameSize::Size4Kb as usize;
+
+static NEXT_INO: AtomicUsize = AtomicUsize::new(1);
+
+// A filesystem kept in memory, the file data lives in frames
+pub struct TmpFs {
+    root: Arc<TmpInode>,
+}
+
+impl TmpFs {
+    pub fn new() -> Self {
+        Self {
+            root: Arc::new(TmpInode::new(InodeType::Dir)),
+        }
+    }
+}
+
+impl FileSystem for TmpFs {
+    fn root(&self) -> Arc<dyn Inode> {
+        self.root.clone()
+    }
+}
+
+enum TmpContent {
+    File { frames: Vec<DataFrame>, size: usize },
+    Dir(BTreeMap<String, Arc<TmpInode>>),
+}
+
+struct TmpInode {
+    ino: usize,
+    content: UPSafeCell<TmpContent>,
+}
+
+impl TmpInode {
+    fn new(_type: InodeType) -> Self {
+        let content = match _type {
+            InodeType::Dir => TmpContent::Dir(BTreeMap::new()),
+            _ => TmpContent::File {
+                frames: Vec::new(),
+                size: 0,
+            },
+        };
+        Self {
+            ino: NEXT_INO.fetch_add(1, Ordering::Relaxed),
+            content: unsafe { UPSafeCell::new(content) },
+        }
+    }
+}
+
+// Back the file with enough zeroed frames to hold size bytes
+// The frames are left as they were on failure
+fn grow_frames(frames: &mut Vec<DataFrame>, size: usize) -> Result<(), FsError> {
+    let old_len = frames.len();
+    while frames.len() * FRAME_SIZE < size {
+        let frame = match CURRENT_FRAME_ALLOCATOR
+            .exclusive_access()
+            .allocate_single_frame(FrameSize::Size4Kb)
+        {
+            Ok(frame) => frame,
+            Err(_) => {
+                frames.truncate(old_len);
+                return Err(FsError::NoSpace);
+            }
+        };
+        frame.zero();
+        frames.push(frame);
+    }
+    Ok(())
+}
+
+impl Inode for TmpInode {
+    fn metadata(&self) -> Metadata {
+        let (_type, size) = match &*self.content.exclusive_access() {
+            TmpContent::File { size, .. } => (InodeType::File, *size),
+            TmpContent::Dir(children) => (InodeType::Dir, children.len()),
+        };
+        Metadata {
+            ino: self.ino,
+            _type,
+            size,
+        }
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize, FsError> {
+        let content = self.content.exclusive_access();
+        let (frames, size) = match &*content {
+            TmpContent::File { frames, size } => (frames, *size),
+            TmpContent::Dir(_) => return Err(FsError::IsDir),
+        };
+        let end = core::cmp::min(offset.saturating_add(buf.len()), size);
+        let mut pos = offset;
+        while pos < end {
+            let inner = pos % FRAME_SIZE;
+            let len = core::cmp::min(FRAME_SIZE - inner, end - pos);
+            let data = frames[pos / FRAME_SIZE]
+                .as_slice::<u8>(inner as u64, len as u64)
+                .unwrap();
+            buf[pos - offset..pos - offset + len].copy_from_slice(data);
+            pos += len;
+        }
+        Ok(pos.saturating_sub(offset))
+    }
+    fn write_at(&self, offset: usize, buf: &[u8]) -> Result<usize, FsError> {
+        let mut content = self.content.exclusive_access();
+        let (frames, size) = match &mut *content {
+            TmpContent::File { frames, size } => (frames, size),
+            TmpContent::Dir(_) => return Err(FsError::IsDir),
+        };
+        let end = offset.checked_add(buf.len()).ok_or(FsError::NoSpace)?;
+        grow_frames(frames, end)?;
+        let mut pos = offset;
+        while pos < end {
+            let inner = pos % FRAME_SIZE;
+            let len = core::cmp::min(FRAME_SIZE - inner, end - pos);
+            frames[pos / FRAME_SIZE]
+                .as_slice_mut::<u8>(inner as u64, len as u64)
+                .unwrap()
+                .copy_from_slice(&buf[pos - offset..pos - offset + len]);
+            pos += len;
+        }
+        *size = core::cmp::max(*size, end);
+        Ok(buf.len())
+    }
+    fn truncate(&self, new_size: usize) -> Result<(), FsError> {
+        let mut content = self.content.exclusive_access();
+        let (frames, size) = match &mut *content {
+            TmpContent::File { frames, size } => (frames, size),
+            TmpContent::Dir(_) => return Err(FsError::IsDir),
+        };
+        if new_size > *size {
+            grow_frames(frames, new_size)?;
+        } else {
+            frames.truncate((new_size + FRAME_SIZE - 1) / FRAME_SIZE);
+            // Clear the tail of the last frame for a later growth
+            let inner = new_size % FRAME_SIZE;
+            if let (Some(last), true) = (frames.last(), inner != 0) {
+                last.as_slice_mut::<u8>(inner as u64, (FRAME_SIZE - inner) as u64)
+                    .unwrap()
+                    .fill(0);
+            }
+        }
+        *size = new_size;
+        Ok(())
+    }
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>, FsError> {
+        match &*self.content.exclusive_access() {
+            TmpContent::Dir(children) => match children.get(name) {
+                Some(inode) => Ok(inode.clone()),
+                None => Err(FsError::NotFound),
+            },
+            TmpContent::File { .. } => Err(FsError::NotDir),
+        }
+    }
+    fn create(&self, name: &str, _type: InodeType) -> Result<Arc<dyn Inode>, FsError> {
+        if _type != InodeType::File && _type != InodeType::Dir {
+            return Err(FsError::NotSupported);
+        }
+        match &mut *self.content.exclusive_access() {
+            TmpContent::Dir(children) => {
+                let inode = Arc::new(TmpInode::new(_type));
+                children.insert(name.to_string(), inode.clone());
+                Ok(inode)
+            }
+            TmpContent::File { .. } => Err(FsError::NotDir),
+        }
+    }
+    fn list(&self) -> Result<Vec<DirEntry>, FsError> {
+        match &*self.content.exclusive_access() {
+            TmpContent::Dir(children) => Ok(children
+                .iter()
+                .map(|(_name, _inode)| DirEntry {
+                    name: _name.clone(),
+                    _type: _inode.metadata()._type,
+                })
+                .collect()),
+            TmpContent::File { .. } => Err(FsError::NotDir),
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use super::FsError;
+use crate::up::UPSafeCell;
+use alloc::{
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+
+#[derive(Copy, Clone, Eq, PartialEq, core::fmt::Debug)]
+pub enum InodeType {
+    File,
+    Dir,
+    CharDevice,
+    BlockDevice,
+}
+
+#[derive(Copy, Clone, core::fmt::Debug)]
+pub struct Metadata {
+    pub ino: usize,
+    pub _type: InodeType,
+    pub size: usize,
+}
+
+pub struct DirEntry {
+    pub name: String,
+    pub _type: InodeType,
+}
+
+// A file or a directory of a filesystem.
+// The operations a kind of inode does not support keep the defaults.
+pub trait Inode: Send + Sync {
+    fn metadata(&self) -> Metadata;
+    fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> Result<usize, FsError> {
+        Err(FsError::NotSupported)
+    }
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize, FsError> {
+        Err(FsError::NotSupported)
+    }
+    fn truncate(&self, _size: usize) -> Result<(), FsError> {
+        Err(FsError::NotSupported)
+    }
+    fn lookup(&self, _name: &str) -> Result<Arc<dyn Inode>, FsError> {
+        Err(FsError::NotDir)
+    }
+    fn create(&self, _name: &str, _type: InodeType) -> Result<Arc<dyn Inode>, FsError> {
+        Err(FsError::NotDir)
+    }
+    fn list(&self) -> Result<Vec<DirEntry>, FsError> {
+        Err(FsError::NotDir)
+    }
+    fn ioctl(&self, _cmd: u64, _arg: u64) -> Result<i64, FsError> {
+        Err(FsError::NotSupported)
+    }
+    // A read-only inode can't be opened for writing
+    fn read_only(&self) -> bool {
+        false
+    }
+}
+
+pub trait FileSystem: Send + Sync {
+    fn root(&self) -> Arc<dyn Inode>;
+    // Write the cached data back to the device
+    fn sync(&self) -> Result<(), FsError> {
+        Ok(())
+    }
+}
+
+// A resolved path and its inode
+pub struct Dentry {
+    pub path: String,
+    pub inode: Arc<dyn Inode>,
+}
+
+struct Mount {
+    // The components of the mount point, empty for /
+    point: Vec<String>,
+    fs: Arc<dyn FileSystem>,
+}
+
+lazy_static::lazy_static! {
+    static ref MOUNT_TABLE: UPSafeCell<Vec<Mount>> = unsafe { UPSafeCell::new(Vec::new()) };
+}
+
+// Split the path into components, "." and ".." are resolved in place.
+// A relative path starts from /, there is no working directory.
+pub fn split_path(path: &str) -> Vec<String> {
+    let mut components: Vec<String> = Vec::new();
+    for _c in path.split('/') {
+        match _c {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            _ => components.push(_c.to_string()),
+        }
+    }
+    components
+}
+
+fn join_path(components: &[String]) -> String {
+    let mut path = String::new();
+    for _c in components.iter() {
+        path.push('/');
+        path.push_str(_c);
+    }
+    if path.is_empty() {
+        path.push('/');
+    }
+    path
+}
+
+// Mount fs at path, the mount point of / needs no directory.
+// A later mount on the same point hides the former one.
+pub fn mount(path: &str, fs: Arc<dyn FileSystem>) -> Result<(), FsError> {
+    let point = split_path(path);
+    if !point.is_empty() {
+        let dentry = lookup(path)?;
+        if dentry.inode.metadata()._type != InodeType::Dir {
+            return Err(FsError::NotDir);
+        }
+    }
+    let mut table = MOUNT_TABLE.exclusive_access();
+    table.retain(|_m| _m.point != point);
+    table.push(Mount { point, fs });
+    Ok(())
+}
+
+// The root inode of the innermost mount holding the path
+// and the number of components it covers
+fn find_mount(components: &[String]) -> Result<(Arc<dyn Inode>, usize), FsError> {
+    MOUNT_TABLE
+        .exclusive_access()
+        .iter()
+        .filter(|_m| components.starts_with(&_m.point))
+        .max_by_key(|_m| _m.point.len())
+        .map(|_m| (_m.fs.root(), _m.point.len()))
+        .ok_or(FsError::NotFound)
+}
+
+// Walk the path from the root of the innermost mount holding it
+pub fn lookup(path: &str) -> Result<Dentry, FsError> {
+    let components = split_path(path);
+    let (mut inode, covered) = find_mount(&components)?;
+    for _c in components[covered..].iter() {
+        inode = inode.lookup(_c)?;
+    }
+    Ok(Dentry {
+        path: join_path(&components),
+        inode,
+    })
+}
+
+// Create a file or a directory, fail if the path exists
+pub fn create(path: &str, _type: InodeType) -> Result<Dentry, FsError> {
+    let mut components = split_path(path);
+    let name = components.pop().ok_or(FsError::Exists)?;
+    let parent = lookup(&join_path(&components))?;
+    if parent.inode.lookup(&name).is_ok() {
+        return Err(FsError::Exists);
+    }
+    let inode = parent.inode.create(&name, _type)?;
+    components.push(name);
+    Ok(Dentry {
+        path: join_path(&components),
+        inode,
+    })
+}
+
+// Every filesystem is synced, the last error is returned
+pub fn sync_all() -> Result<(), FsError> {
+    let mut result = Ok(());
+    for _m in MOUNT_TABLE.exclusive_access().iter() {
+        if let Err(e) = _m.fs.sync() {
+            result = Err(e);
+        }
+    }
+    result
+}
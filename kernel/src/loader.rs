@@ -4,8 +4,9 @@ use lazy_static::*;
 use crate::{
     addr_space::VmSpace,
     addr_type::{Addr, UserAddr},
-    arch::paging::PageTableFlags,
-    frame::{DataFrame, FrameObj},
+    arch::{paging::PageTableFlags, PAGE_SIZE},
+    consts::USER_SPACE_END,
+    frame::{DataFrame, FrameObj, FrameSize, LazyFrame},
     frame_allocator::{FrameAllocator, CURRENT_FRAME_ALLOCATOR},
 };
 
@@ -56,12 +57,8 @@ lazy_static! {
     };
 }
 
-#[allow(unused)]
-pub fn get_app_data_by_name(name: &str) -> Option<&'static [u8]> {
-    let num_app = get_num_app();
-    (0..num_app)
-        .find(|&i| APP_NAMES[i] == name)
-        .map(|i| get_app_data(i))
+pub fn get_app_name(app_id: usize) -> &'static str {
+    APP_NAMES[app_id]
 }
 
 pub fn list_apps() {
@@ -72,30 +69,52 @@ pub fn list_apps() {
     println!("**************/");
 }
 
-pub fn elf_mapper(elf_data: &[u8], space: &mut VmSpace) -> UserAddr {
-    let elf = xmas_elf::ElfFile::new(elf_data).unwrap();
+#[derive(core::fmt::Debug)]
+pub enum LoadError {
+    // Not an ELF file or a segment lies outside of the file or the user space
+    InvalidElf,
+    // Out of frames, or the arguments don't fit in the stack
+    NoMemory,
+}
+
+// Map the loadable segments of the ELF into the space.
+// Return the entry point of the program.
+pub fn elf_mapper(elf_data: &[u8], space: &mut VmSpace) -> Result<UserAddr, LoadError> {
+    if elf_data.len() < 4 || elf_data[..4] != [0x7f, 0x45, 0x4c, 0x46] {
+        return Err(LoadError::InvalidElf);
+    }
+    let elf = xmas_elf::ElfFile::new(elf_data).map_err(|_| LoadError::InvalidElf)?;
     let elf_header = elf.header;
-    let magic = elf_header.pt1.magic;
-    assert_eq!(magic, [0x7f, 0x45, 0x4c, 0x46], "invalid elf!");
     let ph_count = elf_header.pt2.ph_count();
+    let mut max_end = 0;
     for i in 0..ph_count {
-        //info!("Loader Hello Elf Segement :{}",i);
-        let ph = elf.program_header(i).unwrap();
-        //info!("===========");
-        //info!("{:?}",ph);
-        if ph.get_type().unwrap() == xmas_elf::program::Type::Load {
+        let ph = elf.program_header(i).map_err(|_| LoadError::InvalidElf)?;
+        let ph_type = ph.get_type().map_err(|_| LoadError::InvalidElf)?;
+        if ph_type == xmas_elf::program::Type::Load {
+            let file_end = ph
+                .offset()
+                .checked_add(ph.file_size())
+                .ok_or(LoadError::InvalidElf)?;
+            let mem_end = ph
+                .virtual_addr()
+                .checked_add(ph.mem_size())
+                .ok_or(LoadError::InvalidElf)?;
+            if file_end > elf.input.len() as u64
+                || ph.file_size() > ph.mem_size()
+                || ph.virtual_addr() % PAGE_SIZE != 0
+                || mem_end > USER_SPACE_END
+            {
+                return Err(LoadError::InvalidElf);
+            }
             let start_va: UserAddr = UserAddr::new(ph.virtual_addr());
-            let end_va: UserAddr = UserAddr::new(ph.virtual_addr() + ph.mem_size());
+            let end_va: UserAddr = UserAddr::new(mem_end);
             let mut flag = PageTableFlags::ATTR_INDEX.val(0)
                 + PageTableFlags::SH::INNERSHARE
                 + PageTableFlags::AF::SET;
             let ph_flags = ph.flags();
-            //info!("{}",ph_flags);
             if ph_flags.is_read() && ph_flags.is_write() {
-                //info!("RW!!!");
                 flag = flag + PageTableFlags::AP::EL0_RW_ELX_RW;
             } else if ph_flags.is_read() {
-                //info!("OR!!!");
                 flag = flag + PageTableFlags::AP::EL0_OR_ELX_OR;
             }
             if !ph_flags.is_execute() {
@@ -105,28 +124,52 @@ pub fn elf_mapper(elf_data: &[u8], space: &mut VmSpace) -> UserAddr {
             }
 
             let len = end_va.addr() - start_va.addr();
-            let mut t = CURRENT_FRAME_ALLOCATOR
-                .exclusive_access()
-                .allocate_frames(start_va, len)
-                .unwrap();
+            if end_va.addr() > max_end {
+                max_end = end_va.addr();
+            }
+            // Only the pages holding file data are allocated now,
+            // the rest is bss and backed on the first access
+            let data_len = (ph.file_size() + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE;
+            let total_len = (len + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE;
+            // Segments may not overlap each other or the stack
+            if !space.is_free(start_va.addr(), total_len) {
+                return Err(LoadError::InvalidElf);
+            }
             let mut frames = Vec::new();
-            //load data
-            let data = &elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize];
-            copy_from_data(&mut t, data);
-            //
-            for _frame in t.into_iter() {
-                frames.push(FrameObj::Data(_frame));
+            if data_len > 0 {
+                let mut t = CURRENT_FRAME_ALLOCATOR
+                    .exclusive_access()
+                    .allocate_frames(start_va, data_len)
+                    .map_err(|_| LoadError::NoMemory)?;
+                //load data
+                let data =
+                    &elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize];
+                copy_from_data(&mut t, data);
+                for _frame in t.into_iter() {
+                    frames.push(FrameObj::Data(_frame));
+                }
+            }
+            for _i in 0..(total_len - data_len) / PAGE_SIZE {
+                frames.push(FrameObj::Lazy(LazyFrame::new(FrameSize::Size4Kb)));
             }
             space.map_range(start_va.addr(), len, frames, Some(flag));
         }
     }
-    UserAddr::new(elf.header.pt2.entry_point())
+    // The heap starts after the last segment
+    space.init_heap((max_end + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE);
+    Ok(UserAddr::new(elf.header.pt2.entry_point()))
 }
 
 fn copy_from_data(frames: &mut Vec<DataFrame>, data: &[u8]) {
     let mut len = data.len();
     let mut pos = 0;
     for _f in frames.iter_mut() {
+        // Recycled frames still hold the data of their last owner,
+        // the part beyond the file data is bss and must read as zero
+        _f.zero();
+        if len == 0 {
+            continue;
+        }
         let l = if len < (_f.frame_size() as usize) {
             len
         } else {
@@ -137,8 +180,5 @@ fn copy_from_data(frames: &mut Vec<DataFrame>, data: &[u8]) {
         dst.clone_from_slice(src);
         pos += l;
         len -= l;
-        if len == 0 {
-            break;
-        }
     }
 }
@@ -22,7 +22,7 @@ use crate::{
     driver::pl01_send,
     frame_allocator::CURRENT_FRAME_ALLOCATOR,
     heap_allocator::init_heap,
-    thread::{SimpleScheduler, CPU, CURRENT_CPU, CURRENT_SCHEDULER},
+    thread::{DefaultScheduler, CPU, CURRENT_CPU, CURRENT_SCHEDULER},
     up::UPSafeCell,
 };
 
@@ -47,10 +47,13 @@ mod consts;
 mod driver;
 mod frame;
 mod frame_allocator;
+mod fs;
 mod heap_allocator;
 mod panic_wait;
+mod process;
 mod syscall;
 mod thread;
+mod time;
 mod up;
 // Contemporary Loader
 
@@ -72,6 +75,9 @@ pub fn kmain() -> ! {
     CURRENT_FRAME_ALLOCATOR.exclusive_access().print_state();
     println!(">>driver test");
     driver::driver_init();
+    time::init();
+    println!(">> Init filesystem");
+    fs::init();
     println!(">> List all app");
     loader::list_apps();
     unsafe {
@@ -81,26 +87,28 @@ pub fn kmain() -> ! {
             .expect("Only init once");
         println!(">> Init scheduler");
         CURRENT_SCHEDULER
-            .try_init_once(|| UPSafeCell::new(SimpleScheduler::new()))
+            .try_init_once(|| UPSafeCell::new(DefaultScheduler::new()))
             .expect("Only init once");
     }
     driver::timer_enable();
     println!(">> Test user mode!");
     {
-        let user_data = loader::get_app_data_by_name("print_A").unwrap();
+        let user_data = fs::read_file("/bin/print_A").unwrap();
         let user_thread = thread::Thread::create_root_user_thread(
-            user_data,
+            &user_data,
             "print_A",
             UserAddr::new(ROOT_THREAD_STACK_BASE),
             ROOT_THREAD_STACK_SIZE,
-        );
-        let user_data_2 = loader::get_app_data_by_name("print_B").unwrap();
+        )
+        .expect("Invalid print_A");
+        let user_data_2 = fs::read_file("/bin/print_B").unwrap();
         let user_thread_2 = thread::Thread::create_root_user_thread(
-            user_data_2,
+            &user_data_2,
             "print_B",
             UserAddr::new(ROOT_THREAD_STACK_BASE),
             ROOT_THREAD_STACK_SIZE,
-        );
+        )
+        .expect("Invalid print_B");
         CURRENT_SCHEDULER
             .try_get()
             .expect("No init")
@@ -0,0 +1,197 @@
+use crate::{
+    addr_space::VmSpace,
+    driver::tty::{tty_on_exit, tty_on_spawn},
+    fs::{open_file, File, OpenFlags},
+    thread::CURRENT_CPU,
+    up::UPSafeCell,
+};
+use alloc::{
+    sync::{Arc, Weak},
+    vec,
+    vec::Vec,
+};
+
+mod pid;
+pub use pid::{pid_alloc, PidHandle};
+
+// Limit of the opened files per process
+const MAX_FD: usize = 256;
+// Range of the nice value, a lower one runs first
+pub const NICE_MIN: i32 = -20;
+pub const NICE_MAX: i32 = 19;
+
+#[derive(core::fmt::Debug)]
+pub enum WaitError {
+    // No child matches the pid
+    NoChild,
+    // The matched children are still running
+    NotExited,
+}
+
+pub struct Process {
+    pid: PidHandle,
+    space: Option<Arc<UPSafeCell<VmSpace>>>,
+    parent: Option<Weak<UPSafeCell<Process>>>,
+    children: Vec<Arc<UPSafeCell<Process>>>,
+    // Some once the process has exited and waits to be reaped
+    exit_code: Option<i32>,
+    // Set by an interrupt, the process exits on its way back to user mode
+    killed: bool,
+    fd_table: Vec<Option<Arc<dyn File>>>,
+    nice: i32,
+}
+
+impl Process {
+    pub fn new(
+        space: Arc<UPSafeCell<VmSpace>>,
+        parent: Option<&Arc<UPSafeCell<Process>>>,
+    ) -> Arc<UPSafeCell<Process>> {
+        // A child inherits the opened files and the nice value of its parent
+        let (fd_table, nice) = match parent {
+            Some(parent) => {
+                let parent = parent.exclusive_access();
+                (parent.fd_table.clone(), parent.nice)
+            }
+            None => (stdio_table(), 0),
+        };
+        let process = Arc::new(unsafe {
+            UPSafeCell::new(Self {
+                pid: pid_alloc(),
+                space: Some(space),
+                parent: parent.map(Arc::downgrade),
+                children: Vec::new(),
+                exit_code: None,
+                killed: false,
+                fd_table,
+                nice,
+            })
+        });
+        let parent_pid = parent.map(|_p| _p.exclusive_access().pid());
+        tty_on_spawn(parent_pid, process.exclusive_access().pid());
+        if let Some(parent) = parent {
+            parent.exclusive_access().children.push(process.clone());
+        }
+        process
+    }
+    pub fn pid(&self) -> usize {
+        self.pid.0
+    }
+    pub fn get_space(&self) -> Option<Arc<UPSafeCell<VmSpace>>> {
+        self.space.clone()
+    }
+    pub fn get_parent(&self) -> Option<Arc<UPSafeCell<Process>>> {
+        self.parent.as_ref().and_then(|_p| _p.upgrade())
+    }
+    pub fn is_zombie(&self) -> bool {
+        self.exit_code.is_some()
+    }
+    pub fn kill(&mut self) {
+        self.killed = true;
+    }
+    pub fn is_killed(&self) -> bool {
+        self.killed
+    }
+    pub fn nice(&self) -> i32 {
+        self.nice
+    }
+    // The value is clamped into the range
+    pub fn set_nice(&mut self, nice: i64) {
+        self.nice = nice.max(NICE_MIN as i64).min(NICE_MAX as i64) as i32;
+    }
+    // Turn the process into a zombie.
+    // The address space is released right away, the pid is kept
+    // until the parent reaps the exit code.
+    pub fn exit(&mut self, exit_code: i32) {
+        self.exit_code = Some(exit_code);
+        self.space.take();
+        self.fd_table.clear();
+        let parent_pid = self.get_parent().map(|_p| _p.exclusive_access().pid());
+        tty_on_exit(self.pid(), parent_pid);
+        // Orphans have nobody left to wait for them
+        for _child in self.children.iter() {
+            _child.exclusive_access().parent = None;
+        }
+        self.children.clear();
+    }
+    pub fn get_file(&self, fd: usize) -> Option<Arc<dyn File>> {
+        self.fd_table.get(fd).and_then(|_f| _f.clone())
+    }
+    // Install the file at the lowest free descriptor
+    pub fn alloc_fd(&mut self, file: Arc<dyn File>) -> Option<usize> {
+        let fd = match self.fd_table.iter().position(|_f| _f.is_none()) {
+            Some(fd) => fd,
+            None if self.fd_table.len() < MAX_FD => {
+                self.fd_table.push(None);
+                self.fd_table.len() - 1
+            }
+            None => return None,
+        };
+        self.fd_table[fd] = Some(file);
+        Some(fd)
+    }
+    // Return false if the descriptor is not opened
+    pub fn close_fd(&mut self, fd: usize) -> bool {
+        match self.fd_table.get_mut(fd) {
+            Some(file) => file.take().is_some(),
+            None => false,
+        }
+    }
+    pub fn dup(&mut self, fd: usize) -> Option<usize> {
+        let file = self.get_file(fd)?;
+        self.alloc_fd(file)
+    }
+    // Make new_fd refer to the file of old_fd, closing new_fd first
+    pub fn dup2(&mut self, old_fd: usize, new_fd: usize) -> Option<usize> {
+        let file = self.get_file(old_fd)?;
+        if new_fd >= MAX_FD {
+            return None;
+        }
+        if new_fd >= self.fd_table.len() {
+            self.fd_table.resize(new_fd + 1, None);
+        }
+        self.fd_table[new_fd] = Some(file);
+        Some(new_fd)
+    }
+    // Reap a zombie child, pid -1 stands for any child.
+    // Return the pid and the exit code of the child.
+    pub fn wait_child(&mut self, pid: isize) -> Result<(usize, i32), WaitError> {
+        if !self
+            .children
+            .iter()
+            .any(|_c| pid == -1 || pid as usize == _c.exclusive_access().pid())
+        {
+            return Err(WaitError::NoChild);
+        }
+        let pos = self.children.iter().position(|_c| {
+            let child = _c.exclusive_access();
+            child.is_zombie() && (pid == -1 || pid as usize == child.pid())
+        });
+        if let Some(pos) = pos {
+            let child = self.children.remove(pos);
+            let child = child.exclusive_access();
+            Ok((child.pid(), child.exit_code.unwrap()))
+        } else {
+            Err(WaitError::NotExited)
+        }
+    }
+}
+
+// stdin, stdout and stderr all refer to the console
+fn stdio_table() -> Vec<Option<Arc<dyn File>>> {
+    let console = open_file("/dev/console", OpenFlags::RDWR).ok();
+    vec![console.clone(), console.clone(), console]
+}
+
+pub fn current_process() -> Arc<UPSafeCell<Process>> {
+    CURRENT_CPU
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .cur_thread()
+        .get_process()
+        .clone()
+}
+
+pub fn current_space() -> Arc<UPSafeCell<VmSpace>> {
+    current_process().exclusive_access().get_space().unwrap()
+}
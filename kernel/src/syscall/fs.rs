@@ -0,0 +1,209 @@
+use super::process::{read_user_str, PATH_MAX};
+use crate::{
+    addr_space::AccessType,
+    addr_type::{Addr, UserAddr},
+    fs::{make_pipe, open_file, vfs, File, OpenFlags},
+    process::{current_process, current_space},
+};
+use alloc::{sync::Arc, vec};
+
+// Size of the kernel buffer a read or write goes through
+const IO_CHUNK: usize = 4096;
+
+fn get_file(fd: u64) -> Option<Arc<dyn File>> {
+    current_process().exclusive_access().get_file(fd as usize)
+}
+
+//63
+// A read returns at most IO_CHUNK bytes
+pub fn sys_read(fd: u64, buf_addr: UserAddr, buf_len: u64) -> i64 {
+    let file = match get_file(fd) {
+        Some(file) if file.readable() => file,
+        _ => return -1,
+    };
+    let mut buf = vec![0u8; core::cmp::min(IO_CHUNK as u64, buf_len) as usize];
+    // The data is gone once read, check the buffer first
+    if current_space()
+        .exclusive_access()
+        .check_access(buf_addr.addr(), buf.len() as u64, AccessType::Write)
+        .is_err()
+    {
+        return -1;
+    }
+    // The file may block, no borrow is held across it
+    let len = match file.read(&mut buf) {
+        Ok(len) => len,
+        Err(_) => return -1,
+    };
+    if current_space()
+        .exclusive_access()
+        .copy_to_user(&buf[..len], buf_addr.addr())
+        .is_err()
+    {
+        return -1;
+    }
+    len as i64
+}
+
+//64
+pub fn sys_write(fd: u64, buf_addr: UserAddr, buf_len: u64) -> i64 {
+    let file = match get_file(fd) {
+        Some(file) if file.writable() => file,
+        _ => return -1,
+    };
+    let mut buf = vec![0u8; core::cmp::min(IO_CHUNK as u64, buf_len) as usize];
+    let mut pos = 0;
+    while pos < buf_len {
+        let l = core::cmp::min(IO_CHUNK as u64, buf_len - pos) as usize;
+        let chunk = &mut buf[..l];
+        if current_space()
+            .exclusive_access()
+            .copy_from_user(chunk, buf_addr.addr() + pos)
+            .is_err()
+        {
+            return -1;
+        }
+        match file.write(chunk) {
+            Ok(0) => break,
+            Ok(len) => pos += len as u64,
+            Err(_) => return -1,
+        }
+    }
+    pos as i64
+}
+
+//61
+// Return the bytes of linux_dirent64 records filled, 0 at the end
+pub fn sys_getdents64(fd: u64, buf_addr: UserAddr, buf_len: u64) -> i64 {
+    let file = match get_file(fd) {
+        Some(file) => file,
+        None => return -1,
+    };
+    let mut buf = vec![0u8; core::cmp::min(IO_CHUNK as u64, buf_len) as usize];
+    // The directory offset moves on, check the buffer first
+    if current_space()
+        .exclusive_access()
+        .check_access(buf_addr.addr(), buf.len() as u64, AccessType::Write)
+        .is_err()
+    {
+        return -1;
+    }
+    let len = match file.getdents(&mut buf) {
+        Ok(len) => len,
+        Err(_) => return -1,
+    };
+    if current_space()
+        .exclusive_access()
+        .copy_to_user(&buf[..len], buf_addr.addr())
+        .is_err()
+    {
+        return -1;
+    }
+    len as i64
+}
+
+//29
+pub fn sys_ioctl(fd: u64, cmd: u64, arg: u64) -> i64 {
+    match get_file(fd) {
+        Some(file) => file.ioctl(cmd, arg).unwrap_or(-1),
+        None => -1,
+    }
+}
+
+//56
+pub fn sys_open(path_addr: UserAddr, path_len: u64, flags: u32) -> i64 {
+    if path_len > PATH_MAX {
+        return -1;
+    }
+    let path = match read_user_str(path_addr, path_len) {
+        Some(path) => path,
+        None => return -1,
+    };
+    let flags = match OpenFlags::from_bits(flags) {
+        Some(flags) => flags,
+        None => return -1,
+    };
+    match open_file(&path, flags) {
+        Ok(file) => current_process()
+            .exclusive_access()
+            .alloc_fd(file)
+            .map_or(-1, |_fd| _fd as i64),
+        Err(_) => -1,
+    }
+}
+
+//57
+pub fn sys_close(fd: u64) -> i64 {
+    if current_process().exclusive_access().close_fd(fd as usize) {
+        0
+    } else {
+        -1
+    }
+}
+
+//23
+pub fn sys_dup(fd: u64) -> i64 {
+    current_process()
+        .exclusive_access()
+        .dup(fd as usize)
+        .map_or(-1, |_fd| _fd as i64)
+}
+
+//24
+// The flags of dup3 are not supported
+pub fn sys_dup3(old_fd: u64, new_fd: u64, flags: u64) -> i64 {
+    if flags != 0 {
+        return -1;
+    }
+    current_process()
+        .exclusive_access()
+        .dup2(old_fd as usize, new_fd as usize)
+        .map_or(-1, |_fd| _fd as i64)
+}
+
+//59
+// Put the fds of the read end and the write end at fds_addr as two i32.
+// The flags of pipe2 are not supported.
+pub fn sys_pipe2(fds_addr: UserAddr, flags: u64) -> i64 {
+    if flags != 0 {
+        return -1;
+    }
+    let (read_end, write_end) = make_pipe();
+    let process = current_process();
+    let mut process = process.exclusive_access();
+    let read_fd = match process.alloc_fd(read_end) {
+        Some(fd) => fd,
+        None => return -1,
+    };
+    let write_fd = match process.alloc_fd(write_end) {
+        Some(fd) => fd,
+        None => {
+            process.close_fd(read_fd);
+            return -1;
+        }
+    };
+    drop(process);
+    let mut fds = [0u8; 8];
+    fds[..4].copy_from_slice(&(read_fd as i32).to_ne_bytes());
+    fds[4..].copy_from_slice(&(write_fd as i32).to_ne_bytes());
+    if current_space()
+        .exclusive_access()
+        .copy_to_user(&fds, fds_addr.addr())
+        .is_err()
+    {
+        let process = current_process();
+        let mut process = process.exclusive_access();
+        process.close_fd(read_fd);
+        process.close_fd(write_fd);
+        return -1;
+    }
+    0
+}
+
+//81
+pub fn sys_sync() -> i64 {
+    match vfs::sync_all() {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
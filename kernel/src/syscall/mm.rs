@@ -0,0 +1,127 @@
+use crate::{
+    addr_space::lazy_frames,
+    addr_type::{Addr, UserAddr},
+    arch::{
+        paging::{PageTableFlags, PageTableFlagsField},
+        PAGE_SIZE,
+    },
+    consts::{USER_MMAP_BASE, USER_SPACE_END},
+    process::current_space,
+};
+
+const PROT_READ: u64 = 1;
+const PROT_WRITE: u64 = 2;
+const PROT_EXEC: u64 = 4;
+
+fn prot_to_flag(prot: u64) -> PageTableFlagsField {
+    let mut flag = PageTableFlags::ATTR_INDEX.val(0)
+        + PageTableFlags::SH::INNERSHARE
+        + PageTableFlags::AF::SET
+        + PageTableFlags::PXN::SET;
+    if prot & PROT_WRITE != 0 {
+        flag = flag + PageTableFlags::AP::EL0_RW_ELX_RW;
+    } else if prot & (PROT_READ | PROT_EXEC) != 0 {
+        flag = flag + PageTableFlags::AP::EL0_OR_ELX_OR;
+    } else {
+        flag = flag + PageTableFlags::AP::EL0_UNACCESS_ELX_RW;
+    }
+    if prot & PROT_EXEC != 0 {
+        flag = flag + PageTableFlags::UXN::CLEAR;
+    } else {
+        flag = flag + PageTableFlags::UXN::SET;
+    }
+    flag
+}
+
+// A single mapping can't be larger than the mmap area
+const MMAP_MAX_LEN: u64 = USER_SPACE_END - USER_MMAP_BASE;
+
+fn page_round_up(len: u64) -> Option<u64> {
+    Some(len.checked_add(PAGE_SIZE - 1)? & !(PAGE_SIZE - 1))
+}
+
+//222
+// Map anonymous memory, the pages are backed on the first access.
+// The kernel chooses the address if addr is 0.
+// Return the address or -1.
+pub fn sys_mmap(addr: UserAddr, len: u64, prot: u64) -> i64 {
+    if len == 0
+        || len > MMAP_MAX_LEN
+        || addr.addr() % PAGE_SIZE != 0
+        || addr.addr() >= USER_SPACE_END
+        || prot & !(PROT_READ | PROT_WRITE | PROT_EXEC) != 0
+    {
+        return -1;
+    }
+    let len = match page_round_up(len) {
+        Some(len) => len,
+        None => return -1,
+    };
+    let space = current_space();
+    let mut space = space.exclusive_access();
+    let va = if addr.addr() == 0 {
+        match space.find_free_area(len) {
+            Some(va) => va,
+            None => return -1,
+        }
+    } else if space.is_free(addr.addr(), len) {
+        addr.addr()
+    } else {
+        return -1;
+    };
+    let frames = match lazy_frames(len) {
+        Some(frames) => frames,
+        None => return -1,
+    };
+    space.map_range(va, len, frames, Some(prot_to_flag(prot)));
+    va as i64
+}
+
+//215
+pub fn sys_munmap(addr: UserAddr, len: u64) -> i64 {
+    if len == 0 || addr.addr() % PAGE_SIZE != 0 {
+        return -1;
+    }
+    let len = match page_round_up(len) {
+        Some(len) => len,
+        None => return -1,
+    };
+    match current_space().exclusive_access().unmap(addr.addr(), len) {
+        Ok(_) => 0,
+        Err(_) => -1,
+    }
+}
+
+//226
+pub fn sys_mprotect(addr: UserAddr, len: u64, prot: u64) -> i64 {
+    if len == 0 || addr.addr() % PAGE_SIZE != 0 || prot & !(PROT_READ | PROT_WRITE | PROT_EXEC) != 0
+    {
+        return -1;
+    }
+    let len = match page_round_up(len) {
+        Some(len) => len,
+        None => return -1,
+    };
+    match current_space()
+        .exclusive_access()
+        .remap(addr.addr(), len, prot_to_flag(prot))
+    {
+        Ok(_) => 0,
+        Err(_) => -1,
+    }
+}
+
+//214
+// Return the new program break, or the current one if addr is 0
+// or the heap can't be moved there
+pub fn sys_brk(addr: UserAddr) -> i64 {
+    let space = current_space();
+    let mut space = space.exclusive_access();
+    if addr.addr() == 0 {
+        return space.get_brk() as i64;
+    }
+    match space.set_brk(addr.addr()) {
+        Some(brk) => brk as i64,
+        None => space.get_brk() as i64,
+    }
+}
@@ -0,0 +1,230 @@
+use crate::{
+    addr_space::AccessType,
+    addr_type::{Addr, UserAddr},
+    consts::{ROOT_THREAD_STACK_BASE, ROOT_THREAD_STACK_SIZE},
+    fs,
+    process::{current_process, current_space, WaitError},
+    thread::{self, Thread, CURRENT_CPU, CURRENT_SCHEDULER},
+};
+use alloc::{format, string::String, sync::Arc, vec::Vec};
+
+const PRIO_PROCESS: u64 = 0;
+
+// Limit of the argument strings passed to exec
+const MAX_ARGS_SIZE: usize = 4096;
+// Limit of a path passed to open, spawn and exec
+pub(super) const PATH_MAX: u64 = 256;
+
+//93
+pub fn sys_exit(exit_code: i32) -> ! {
+    thread::_exit(exit_code)
+}
+
+//124
+pub fn sys_yield() -> i64 {
+    thread::_yield();
+    0
+}
+
+//172
+pub fn sys_getpid() -> i64 {
+    current_process().exclusive_access().pid() as i64
+}
+
+//140
+// Only which = PRIO_PROCESS is supported, who = 0 is the caller,
+// any other target must be a child of the caller.
+// The nice value is clamped into -20..=19, the queued threads
+// of the target move to their new level right away.
+pub fn sys_setpriority(which: u64, who: u64, nice: i64) -> i64 {
+    if which != PRIO_PROCESS {
+        return -1;
+    }
+    let process = if who == 0 {
+        Some(current_process())
+    } else {
+        thread::find_process(who as usize)
+    };
+    let process = match process {
+        Some(process) => process,
+        None => return -1,
+    };
+    let caller = current_process();
+    let is_child = process
+        .exclusive_access()
+        .get_parent()
+        .map_or(false, |_p| Arc::ptr_eq(&_p, &caller));
+    if !Arc::ptr_eq(&process, &caller) && !is_child {
+        return -1;
+    }
+    process.exclusive_access().set_nice(nice);
+    thread::renice();
+    0
+}
+
+//260
+// -1: no child matches the pid
+// -2: the child has not exited yet
+pub fn sys_waitpid(pid: i64, exit_code_addr: UserAddr) -> i64 {
+    // The zombie is gone once reaped, check the destination first
+    if exit_code_addr.addr() != 0
+        && current_space()
+            .exclusive_access()
+            .check_access(
+                exit_code_addr.addr(),
+                core::mem::size_of::<i32>() as u64,
+                AccessType::Write,
+            )
+            .is_err()
+    {
+        return -1;
+    }
+    let process = current_process();
+    let r = process.exclusive_access().wait_child(pid as isize);
+    match r {
+        Ok((child_pid, exit_code)) => {
+            if exit_code_addr.addr() != 0
+                && current_space()
+                    .exclusive_access()
+                    .copy_to_user(&exit_code.to_ne_bytes(), exit_code_addr.addr())
+                    .is_err()
+            {
+                return -1;
+            }
+            child_pid as i64
+        }
+        Err(WaitError::NoChild) => -1,
+        Err(WaitError::NotExited) => -2,
+    }
+}
+
+// Read a string which is not NUL terminated from the current space
+pub(super) fn read_user_str(addr: UserAddr, len: u64) -> Option<String> {
+    current_space()
+        .exclusive_access()
+        .read_user_str(addr.addr(), len)
+        .ok()
+}
+
+// Read the ELF of an app from the filesystem.
+// A bare name is searched in /bin.
+fn load_app(path: &str) -> Option<Vec<u8>> {
+    if path.contains('/') {
+        fs::read_file(path).ok()
+    } else {
+        fs::read_file(&format!("/bin/{}", path)).ok()
+    }
+}
+
+//170
+// The path is not NUL terminated, the caller passes its length.
+// Return the pid of the child or -1 if there is no such app.
+pub fn sys_spawn(path_addr: UserAddr, path_len: u64) -> i64 {
+    let process = current_process();
+    if path_len > PATH_MAX {
+        return -1;
+    }
+    let path = match read_user_str(path_addr, path_len) {
+        Some(path) => path,
+        None => return -1,
+    };
+    let elf_data = match load_app(&path) {
+        Some(elf_data) => elf_data,
+        None => return -1,
+    };
+    let t = match Thread::create_user_thread(
+        &elf_data,
+        &path,
+        &[&path],
+        UserAddr::new(ROOT_THREAD_STACK_BASE),
+        ROOT_THREAD_STACK_SIZE,
+        Some(&process),
+    ) {
+        Ok(t) => t,
+        Err(_) => return -1,
+    };
+    let pid = t.get_process().exclusive_access().pid();
+    CURRENT_SCHEDULER
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .push_thread(t);
+    pid as i64
+}
+
+//220
+// The child gets a COW copy of the address space
+pub fn sys_fork() -> i64 {
+    let t = CURRENT_CPU
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .cur_thread()
+        .fork();
+    let pid = t.get_process().exclusive_access().pid();
+    CURRENT_SCHEDULER
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .push_thread(t);
+    pid as i64
+}
+
+// Read the (ptr, len) pairs of the arguments from the current space
+fn read_user_args(args_addr: UserAddr, argc: u64) -> Option<Vec<String>> {
+    let space = current_space();
+    let mut args = Vec::new();
+    let mut total = 0;
+    for _i in 0..argc {
+        let mut pair = [0u8; 16];
+        space
+            .exclusive_access()
+            .copy_from_user(&mut pair, args_addr.addr() + _i * 16)
+            .ok()?;
+        let ptr = u64::from_ne_bytes(pair[0..8].try_into().unwrap());
+        let len = u64::from_ne_bytes(pair[8..16].try_into().unwrap());
+        total = (len as usize)
+            .checked_add(total + 1)
+            .filter(|_t| *_t <= MAX_ARGS_SIZE)?;
+        args.push(read_user_str(UserAddr::new(ptr), len)?);
+    }
+    Some(args)
+}
+
+//221
+// The arguments are passed as an array of (ptr, len) pairs.
+// Return -1 if the app is missing or invalid, otherwise the caller
+// keeps its pid and starts over at the entry of the app.
+pub fn sys_exec(path_addr: UserAddr, path_len: u64, args_addr: UserAddr, argc: u64) -> i64 {
+    if path_len > PATH_MAX {
+        return -1;
+    }
+    let path = match read_user_str(path_addr, path_len) {
+        Some(path) => path,
+        None => return -1,
+    };
+    let args = match read_user_args(args_addr, argc) {
+        Some(args) => args,
+        None => return -1,
+    };
+    let elf_data = match load_app(&path) {
+        Some(elf_data) => elf_data,
+        None => return -1,
+    };
+    let args: Vec<&str> = args.iter().map(|_a| _a.as_str()).collect();
+    let loaded = CURRENT_CPU
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .cur_thread_mut()
+        .exec(
+            &elf_data,
+            &args,
+            UserAddr::new(ROOT_THREAD_STACK_BASE),
+            ROOT_THREAD_STACK_SIZE,
+        );
+    match loaded {
+        Ok(_) => 0,
+        Err(_) => -1,
+    }
+}
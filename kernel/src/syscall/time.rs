@@ -0,0 +1,107 @@
+use crate::{
+    addr_type::{Addr, UserAddr},
+    driver::timer,
+    process::{current_process, current_space},
+    thread::sleep_timeout,
+    time::{self, NSEC_PER_SEC},
+};
+
+// The clocks of clock_gettime
+const CLOCK_REALTIME: u64 = 0;
+const CLOCK_MONOTONIC: u64 = 1;
+const CLOCK_BOOTTIME: u64 = 7;
+
+// The struct timespec of the user
+#[derive(Copy, Clone)]
+struct TimeSpec {
+    sec: i64,
+    nsec: i64,
+}
+
+impl TimeSpec {
+    fn read_from_user(addr: UserAddr) -> Option<Self> {
+        let mut buf = [0u8; 16];
+        current_space()
+            .exclusive_access()
+            .copy_from_user(&mut buf, addr.addr())
+            .ok()?;
+        Some(Self {
+            sec: i64::from_ne_bytes(buf[..8].try_into().unwrap()),
+            nsec: i64::from_ne_bytes(buf[8..].try_into().unwrap()),
+        })
+    }
+
+    fn is_valid(&self) -> bool {
+        self.sec >= 0 && self.nsec >= 0 && self.nsec < NSEC_PER_SEC as i64
+    }
+
+    fn as_ns(&self) -> u64 {
+        (self.sec as u64)
+            .saturating_mul(NSEC_PER_SEC)
+            .saturating_add(self.nsec as u64)
+    }
+
+    fn from_ns(ns: u64) -> Self {
+        Self {
+            sec: (ns / NSEC_PER_SEC) as i64,
+            nsec: (ns % NSEC_PER_SEC) as i64,
+        }
+    }
+}
+
+// Put the 16 bytes of a struct timespec or timeval
+fn write_pair(addr: UserAddr, first: i64, second: i64) -> i64 {
+    let mut buf = [0u8; 16];
+    buf[..8].copy_from_slice(&first.to_ne_bytes());
+    buf[8..].copy_from_slice(&second.to_ne_bytes());
+    match current_space()
+        .exclusive_access()
+        .copy_to_user(&buf, addr.addr())
+    {
+        Ok(_) => 0,
+        Err(_) => -1,
+    }
+}
+
+//113
+pub fn sys_clock_gettime(clock_id: u64, tp_addr: UserAddr) -> i64 {
+    let ns = match clock_id {
+        CLOCK_REALTIME => time::realtime_ns(),
+        CLOCK_MONOTONIC | CLOCK_BOOTTIME => time::monotonic_ns(),
+        _ => return -1,
+    };
+    let tp = TimeSpec::from_ns(ns);
+    write_pair(tp_addr, tp.sec, tp.nsec)
+}
+
+//169
+// The timezone is not supported, tz_addr is ignored
+pub fn sys_gettimeofday(tv_addr: UserAddr, _tz_addr: UserAddr) -> i64 {
+    let tp = TimeSpec::from_ns(time::realtime_ns());
+    write_pair(tv_addr, tp.sec, tp.nsec / 1000)
+}
+
+//101
+// Return -1 if the caller is killed while sleeping,
+// the time left is put at rem_addr unless it is 0
+pub fn sys_nanosleep(req_addr: UserAddr, rem_addr: UserAddr) -> i64 {
+    let req = match TimeSpec::read_from_user(req_addr) {
+        Some(req) if req.is_valid() => req,
+        _ => return -1,
+    };
+    let deadline = timer::counter().saturating_add(time::ns_to_counter(req.as_ns()));
+    // An address on the kernel stack is a chanel nobody else sleeps on
+    let chan = &deadline as *const u64 as usize;
+    while timer::counter() < deadline {
+        if current_process().exclusive_access().is_killed() {
+            if rem_addr.addr() != 0 {
+                let left = deadline.saturating_sub(timer::counter());
+                let rem = TimeSpec::from_ns(time::counter_to_ns(left));
+                write_pair(rem_addr, rem.sec, rem.nsec);
+            }
+            return -1;
+        }
+        sleep_timeout(chan, deadline);
+    }
+    0
+}
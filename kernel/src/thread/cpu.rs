@@ -1,15 +1,24 @@
 use conquer_once::spin::OnceCell;
 
-use crate::{thread::ThreadState, up::UPSafeCell};
+use crate::{arch::cpu::wait_for_irq, driver::timer, thread::ThreadState, up::UPSafeCell};
 
 use super::Thread;
 pub static CURRENT_CPU: OnceCell<UPSafeCell<CPU>> = OnceCell::uninit();
 pub struct CPU {
     pub cur_thread: Option<Thread>,
+    // The counter ticks spent waiting for an irq
+    idle_count: u64,
 }
 impl CPU {
     pub fn new() -> Self {
-        Self { cur_thread: None }
+        Self {
+            cur_thread: None,
+            idle_count: 0,
+        }
+    }
+    // Milliseconds spent idle since boot
+    pub fn idle_ms(&self) -> u64 {
+        timer::counter_to_ms(self.idle_count)
     }
     pub fn reset(&mut self) -> Thread {
         assert!(self.cur_thread.is_some());
@@ -18,7 +27,29 @@ impl CPU {
         self.cur_thread = None;
         t.unwrap()
     }
+    pub fn take_current(&mut self) -> Thread {
+        self.cur_thread.take().expect("No current thread")
+    }
     pub fn cur_thread(&self) -> &Thread {
         &self.cur_thread.as_ref().unwrap()
     }
+    pub fn cur_thread_mut(&mut self) -> &mut Thread {
+        self.cur_thread.as_mut().unwrap()
+    }
+}
+
+// Run when no thread is ready, sleep until an irq may wake one up.
+// The cpu is not borrowed while waiting, the irq handlers use it.
+// There is nothing to preempt, only the timer events wake it up.
+pub fn idle() {
+    let start = timer::counter();
+    timer::tick_disable();
+    wait_for_irq();
+    timer::timer_enable();
+    let count = timer::counter() - start;
+    CURRENT_CPU
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .idle_count += count;
 }
@@ -1,13 +1,15 @@
 use crate::{
     addr_space::VmSpace,
     addr_type::{Addr, KernelAddr, PhysAddr, UserAddr},
-    arch::{paging::PageTableFlags, UserCtx},
-    frame::{FrameObj, FrameSize, GuardFrame},
+    arch::{paging::PageTableFlags, switch_to_vmspace, RegType, UserCtx, PAGE_SIZE},
+    consts::USER_STACK_MAX_SIZE,
+    frame::{FrameObj, FrameSize, GuardFrame, LazyFrame},
     frame_allocator::{FrameAllocator, CURRENT_FRAME_ALLOCATOR},
-    loader,
+    loader::{self, LoadError},
+    process::Process,
     up::UPSafeCell,
 };
-use alloc::{sync::Arc, vec::Vec};
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
 use core::borrow::Borrow;
 use kernel_stack::KernelStack;
 use ruspiro_lock::Spinlock;
@@ -18,9 +20,18 @@ mod scheduler;
 mod thread_ctx;
 pub use cpu::CPU;
 pub use cpu::CURRENT_CPU;
+pub use scheduler::_exit;
 pub use scheduler::_yield;
+pub use scheduler::exit_if_killed;
+pub use scheduler::find_process;
+pub use scheduler::kill;
+pub use scheduler::on_tick;
+pub use scheduler::renice;
 pub use scheduler::sched;
-pub use scheduler::SimpleScheduler;
+pub use scheduler::sleep;
+pub use scheduler::sleep_timeout;
+pub use scheduler::wakeup;
+pub use scheduler::DefaultScheduler;
 pub use scheduler::CURRENT_SCHEDULER;
 pub use thread_ctx::thread_swtch;
 
@@ -40,11 +51,17 @@ pub enum ThreadType {
 }
 pub struct Thread {
     pub _type: ThreadType,
-    pub context: thread_ctx::ThreadCtx,
+    // Boxed so that the saved registers keep their address
+    // while the thread moves between the cpu and the run queue
+    pub context: Box<thread_ctx::ThreadCtx>,
     pub lock: Spinlock,
     pub chanel: Option<usize>,
     pub state: ThreadState,
+    pub exit_code: i32,
+    // Ticks run in the current time slice
+    pub ticks: usize,
     space: Option<Arc<UPSafeCell<VmSpace>>>,
+    process: Option<Arc<UPSafeCell<Process>>>,
     kernel_stack: Option<KernelStack>,
 }
 
@@ -52,11 +69,14 @@ impl Default for Thread {
     fn default() -> Self {
         Thread {
             _type: ThreadType::KERNEL,
-            context: thread_ctx::ThreadCtx::new(),
+            context: Box::new(thread_ctx::ThreadCtx::new()),
             lock: Spinlock::new(),
             chanel: None,
             state: ThreadState::UNINIT,
+            exit_code: 0,
+            ticks: 0,
             space: None,
+            process: None,
             kernel_stack: None,
         }
     }
@@ -68,11 +88,14 @@ impl Thread {
     pub fn create_root_kernel_thread() -> Self {
         Self {
             _type: ThreadType::KERNEL,
-            context: thread_ctx::ThreadCtx::new(),
+            context: Box::new(thread_ctx::ThreadCtx::new()),
             lock: Spinlock::new(),
             chanel: None,
             state: ThreadState::RUNNING,
+            exit_code: 0,
+            ticks: 0,
             space: None,
+            process: None,
             kernel_stack: None,
         }
     }
@@ -83,36 +106,34 @@ impl Thread {
         bin_name: &str,
         stack_base: UserAddr,
         stack_size: u64,
-    ) -> Self {
+    ) -> Result<Self, LoadError> {
         println!("Create root user thread {}", bin_name);
+        Self::create_user_thread(
+            elf_data,
+            bin_name,
+            &[bin_name],
+            stack_base,
+            stack_size,
+            None,
+        )
+    }
+    //create user thread in a new process
+    //the process is a child of parent if given
+    pub fn create_user_thread(
+        elf_data: &[u8],
+        bin_name: &str,
+        args: &[&str],
+        stack_base: UserAddr,
+        stack_size: u64,
+        parent: Option<&Arc<UPSafeCell<Process>>>,
+    ) -> Result<Self, LoadError> {
         let mut space: VmSpace = VmSpace::new();
         //Init User Stack
-        let t = CURRENT_FRAME_ALLOCATOR
-            .exclusive_access()
-            .allocate_frames(stack_base, stack_size)
-            .unwrap();
-        let mut stack_frames = Vec::new();
-
-        for _frame in t.into_iter() {
-            stack_frames.push(FrameObj::Data(_frame));
-        }
-        stack_frames.push(FrameObj::Guard(GuardFrame::new(FrameSize::Size4Kb)));
-        let _stack_flag = PageTableFlags::ATTR_INDEX.val(0)
-            + PageTableFlags::SH::INNERSHARE
-            + PageTableFlags::AP::EL0_RW_ELX_RW
-            + PageTableFlags::UXN::SET
-            + PageTableFlags::PXN::SET
-            + PageTableFlags::AF::SET;
-
-        space.map_range(
-            stack_base.addr(),
-            stack_size,
-            stack_frames,
-            Some(_stack_flag),
-        );
+        map_user_stack(&mut space, stack_base, stack_size);
 
         //Load Binary
-        let pc = loader::elf_mapper(elf_data, &mut space);
+        let pc = loader::elf_mapper(elf_data, &mut space)?;
+        let (sp, argv) = push_args(&space, stack_base + stack_size, args)?;
 
         //Init Kernel Stack
         let kernel_stack_frame = CURRENT_FRAME_ALLOCATOR
@@ -121,26 +142,89 @@ impl Thread {
             .unwrap();
         let mut kernel_stack = KernelStack::new(kernel_stack_frame);
         let mut user_ctx = UserCtx::new();
-        user_ctx.user_init(stack_base + stack_size, pc);
+        user_ctx.user_init(sp, pc);
+        user_ctx.set_args(args.len() as u64, argv);
+        kernel_stack.push_on(user_ctx);
+        let mut thread_ctx = thread_ctx::ThreadCtx::new();
+        thread_ctx.set(kernel_stack.sp().addr(), ThreadType::USER, 0);
+        let space = Arc::new(unsafe { UPSafeCell::new(space) });
+        let process = Process::new(space.clone(), parent);
+        debug!("{} runs as pid {}", bin_name, process.exclusive_access().pid());
+        Ok(Self {
+            _type: ThreadType::USER,
+            context: Box::new(thread_ctx),
+            lock: ruspiro_lock::Spinlock::new(),
+            chanel: None,
+            state: ThreadState::READY,
+            exit_code: 0,
+            ticks: 0,
+            space: Some(space),
+            process: Some(process),
+            kernel_stack: Some(kernel_stack),
+        })
+    }
+    //fork the user thread into a new child process
+    //the child returns 0 from the syscall
+    pub fn fork(&self) -> Self {
+        let space = self.get_space().exclusive_access().fork();
+        let space = Arc::new(unsafe { UPSafeCell::new(space) });
+        let process = Process::new(space.clone(), Some(self.get_process()));
+
+        //Copy the trap frame to the new kernel stack
+        let kernel_stack_frame = CURRENT_FRAME_ALLOCATOR
+            .exclusive_access()
+            .allocate_single_frame(FrameSize::Size4Kb)
+            .unwrap();
+        let mut kernel_stack = KernelStack::new(kernel_stack_frame);
+        let mut user_ctx = unsafe { &*(self.get_kernel_stack().addr() as *const UserCtx) }.clone();
+        user_ctx[RegType::X0] = 0;
         kernel_stack.push_on(user_ctx);
         let mut thread_ctx = thread_ctx::ThreadCtx::new();
         thread_ctx.set(kernel_stack.sp().addr(), ThreadType::USER, 0);
-        //println!("SP:{:?}\n",&kernel_stack.sp());
-        //println!("{:?}\n",&space);
-        //space.print_page_table();
         Self {
             _type: ThreadType::USER,
-            context: thread_ctx,
+            context: Box::new(thread_ctx),
             lock: ruspiro_lock::Spinlock::new(),
             chanel: None,
             state: ThreadState::READY,
-            space: Some(Arc::new(unsafe { UPSafeCell::new(space) })),
+            exit_code: 0,
+            ticks: 0,
+            space: Some(space),
+            process: Some(process),
             kernel_stack: Some(kernel_stack),
         }
     }
+    //replace the address space with a new program
+    //the thread returns to the entry of the program
+    //the old space is kept if the program can't be loaded
+    pub fn exec(
+        &mut self,
+        elf_data: &[u8],
+        args: &[&str],
+        stack_base: UserAddr,
+        stack_size: u64,
+    ) -> Result<(), LoadError> {
+        let mut space = VmSpace::new();
+        map_user_stack(&mut space, stack_base, stack_size);
+        let pc = loader::elf_mapper(elf_data, &mut space)?;
+        let (sp, argv) = push_args(&space, stack_base + stack_size, args)?;
+        let pagetable = space.get_pagetable();
+        let old = core::mem::replace(&mut *self.get_space().exclusive_access(), space);
+        // Leave the old tables before they are released
+        switch_to_vmspace(pagetable);
+        drop(old);
+        let user_ctx = unsafe { &mut *(self.get_kernel_stack().addr() as *mut UserCtx) };
+        *user_ctx = UserCtx::new();
+        user_ctx.user_init(sp, pc);
+        user_ctx.set_args(args.len() as u64, argv);
+        Ok(())
+    }
     pub fn get_space(&self) -> &UPSafeCell<VmSpace> {
         self.space.as_ref().unwrap().borrow()
     }
+    pub fn get_process(&self) -> &Arc<UPSafeCell<Process>> {
+        self.process.as_ref().unwrap()
+    }
     pub fn get_pagetable(&self) -> PhysAddr {
         self.space
             .as_ref()
@@ -149,7 +233,85 @@ impl Thread {
             .exclusive_access()
             .get_pagetable()
     }
+    // The nice value of the process, 0 for a kernel thread
+    pub fn nice(&self) -> i32 {
+        self.process
+            .as_ref()
+            .map_or(0, |_p| _p.exclusive_access().nice())
+    }
     pub fn get_kernel_stack(&self) -> KernelAddr {
         self.kernel_stack.as_ref().unwrap().sp()
     }
+    // Drop the address space so that the frames of every region
+    // and the page table go back to the frame allocator.
+    // The process becomes a zombie until its parent reaps it.
+    // The kernel stack is still in use until the thread switches
+    // out, it is released when the scheduler reaps the thread.
+    pub fn release_space(&mut self) {
+        self.space.take();
+        if let Some(process) = self.process.as_ref() {
+            process.exclusive_access().exit(self.exit_code);
+        }
+    }
+}
+
+// The stack is mapped lazily, a page is backed on its first access
+fn map_user_stack(space: &mut VmSpace, stack_base: UserAddr, stack_size: u64) {
+    let mut stack_frames = Vec::new();
+    for _i in 0..(stack_size + PAGE_SIZE - 1) / PAGE_SIZE {
+        stack_frames.push(FrameObj::Lazy(LazyFrame::new(FrameSize::Size4Kb)));
+    }
+    stack_frames.push(FrameObj::Guard(GuardFrame::new(FrameSize::Size4Kb)));
+    let _stack_flag = PageTableFlags::ATTR_INDEX.val(0)
+        + PageTableFlags::SH::INNERSHARE
+        + PageTableFlags::AP::EL0_RW_ELX_RW
+        + PageTableFlags::UXN::SET
+        + PageTableFlags::PXN::SET
+        + PageTableFlags::AF::SET;
+
+    space.map_range(
+        stack_base.addr(),
+        stack_size,
+        stack_frames,
+        Some(_stack_flag),
+    );
+    let stack_top = stack_base.addr() + stack_size;
+    space.set_stack(stack_base.addr(), stack_top - USER_STACK_MAX_SIZE);
+}
+
+// Lay out the arguments at the top of the user stack
+// | argv[0] .. argv[argc-1] | NULL | strings | <- stack top
+// Return the new stack top and the address of argv
+fn push_args(
+    space: &VmSpace,
+    stack_top: UserAddr,
+    args: &[&str],
+) -> Result<(UserAddr, u64), LoadError> {
+    let mut sp = stack_top.addr();
+    let mut ptrs = Vec::new();
+    for _arg in args.iter() {
+        sp = sp
+            .checked_sub(_arg.len() as u64 + 1)
+            .ok_or(LoadError::NoMemory)?;
+        space
+            .copy_to_user(_arg.as_bytes(), sp)
+            .map_err(|_| LoadError::NoMemory)?;
+        space
+            .copy_to_user(&[0], sp + _arg.len() as u64)
+            .map_err(|_| LoadError::NoMemory)?;
+        ptrs.push(sp);
+    }
+    ptrs.push(0);
+    sp = sp
+        .checked_sub((ptrs.len() * core::mem::size_of::<u64>()) as u64)
+        .ok_or(LoadError::NoMemory)?;
+    // The stack pointer is 16 bytes aligned
+    sp &= !0xf;
+    for (_pos, _ptr) in ptrs.iter().enumerate() {
+        let addr = sp + (_pos * core::mem::size_of::<u64>()) as u64;
+        space
+            .copy_to_user(&_ptr.to_ne_bytes(), addr)
+            .map_err(|_| LoadError::NoMemory)?;
+    }
+    Ok((UserAddr::new(sp), sp))
 }
@@ -0,0 +1,378 @@
+use super::{
+    cpu::{idle, CURRENT_CPU},
+    thread_ctx::ThreadCtx,
+    thread_swtch, ThreadState,
+};
+use crate::{
+    arch::switch_to_vmspace,
+    driver::timer,
+    process::Process,
+    thread::Thread,
+    up::UPSafeCell,
+};
+use alloc::{sync::Arc, vec::Vec};
+use conquer_once::spin::OnceCell;
+
+mod priority;
+mod round_robin;
+pub use priority::PriorityScheduler;
+pub use round_robin::RoundRobin;
+
+// The exit code of a killed process
+const EXIT_KILLED: i32 = -3;
+
+//---------- Scheduler Trait -----------
+// The policy choosing among the runnable threads.
+// The waiting and the exited threads are kept by ThreadScheduler.
+pub trait Scheduler: Default {
+    // Queue a runnable thread
+    fn enqueue(&mut self, t: Thread);
+    // Take the thread to run next
+    fn pick_next(&mut self) -> Option<Thread>;
+    // Called on a timer tick with the running thread,
+    // return true if it should give up the cpu
+    fn on_tick(&mut self, cur: &mut Thread) -> bool;
+    // The thread is going to sleep
+    fn on_block(&mut self, _t: &mut Thread) {}
+    // The thread is woken up and queued right after,
+    // it starts a fresh time slice
+    fn on_wake(&mut self, t: &mut Thread) {
+        t.ticks = 0;
+    }
+    // The nice value of some queued threads changed
+    fn on_renice(&mut self) {}
+    // Visit the queued threads
+    fn for_each(&mut self, f: &mut dyn FnMut(&mut Thread));
+}
+
+// The policy is chosen at build time
+#[cfg(not(feature = "sched_priority"))]
+pub type DefaultScheduler = ThreadScheduler<RoundRobin>;
+#[cfg(feature = "sched_priority")]
+pub type DefaultScheduler = ThreadScheduler<PriorityScheduler>;
+
+//---------------------------------------
+pub static CURRENT_SCHEDULER: OnceCell<UPSafeCell<DefaultScheduler>> = OnceCell::uninit();
+
+pub struct ThreadScheduler<S: Scheduler> {
+    policy: S,
+    waiting: Vec<Thread>,
+    exited: Vec<Thread>,
+    sched_thread: Thread,
+}
+
+impl<S: Scheduler> ThreadScheduler<S> {
+    pub fn sched_context(&self) -> &ThreadCtx {
+        &self.sched_thread.context
+    }
+
+    pub fn new() -> Self {
+        Self {
+            policy: S::default(),
+            waiting: Vec::new(),
+            exited: Vec::new(),
+            sched_thread: Thread::create_root_kernel_thread(),
+        }
+    }
+
+    // Take back a thread, where it goes depends on its state
+    pub fn push_thread(&mut self, mut t: Thread) {
+        match t.state {
+            ThreadState::WAITING => {
+                self.policy.on_block(&mut t);
+                self.waiting.push(t);
+            }
+            ThreadState::EXITING => self.exited.push(t),
+            _ => {
+                t.state = ThreadState::READY;
+                self.policy.enqueue(t);
+            }
+        }
+    }
+
+    pub fn sched_next(&mut self) -> Option<Thread> {
+        let mut t = self.policy.pick_next()?;
+        t.state = ThreadState::RUNNING;
+        Some(t)
+    }
+
+    // Queue the waiting threads matched by f again
+    fn wake_if(&mut self, f: impl Fn(&Thread) -> bool) {
+        let mut pos = 0;
+        while pos < self.waiting.len() {
+            if f(&self.waiting[pos]) {
+                let mut t = self.waiting.remove(pos);
+                t.chanel = None;
+                t.state = ThreadState::READY;
+                self.policy.on_wake(&mut t);
+                self.policy.enqueue(t);
+            } else {
+                pos += 1;
+            }
+        }
+    }
+
+    // Make the threads sleeping on chan runnable again
+    pub fn wakeup(&mut self, chan: usize) {
+        self.wake_if(|_t| _t.chanel == Some(chan));
+    }
+
+    // Return true if the running thread should give up the cpu
+    pub fn tick(&mut self, cur: &mut Thread) -> bool {
+        self.policy.on_tick(cur)
+    }
+
+    pub fn renice(&mut self) {
+        self.policy.on_renice();
+    }
+
+    // The process of a queued or waiting thread
+    pub fn find_process(&mut self, pid: usize) -> Option<Arc<UPSafeCell<Process>>> {
+        let mut found = None;
+        let mut matches = |_t: &mut Thread| {
+            if let Some(process) = _t.process.as_ref() {
+                if found.is_none() && process.exclusive_access().pid() == pid {
+                    found = Some(process.clone());
+                }
+            }
+        };
+        self.waiting.iter_mut().for_each(&mut matches);
+        self.policy.for_each(&mut matches);
+        found
+    }
+
+    // Mark the process killed and wake up its sleeping threads.
+    // Return false if no thread belongs to the process.
+    pub fn kill(&mut self, pid: usize) -> bool {
+        let process = match self.find_process(pid) {
+            Some(process) => process,
+            None => return false,
+        };
+        process.exclusive_access().kill();
+        self.wake_if(|_t| _t.process.as_ref().map_or(false, |_p| Arc::ptr_eq(_p, &process)));
+        true
+    }
+
+    // Drop the exited threads together with their kernel stacks.
+    // Only called on the scheduler stack.
+    pub fn reap_exited(&mut self) {
+        self.exited.clear();
+    }
+}
+
+pub fn sched() {
+    let scheduler_context = CURRENT_SCHEDULER
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .sched_context()
+        .get_raw_addr();
+    loop {
+        CURRENT_SCHEDULER
+            .try_get()
+            .expect("No init")
+            .exclusive_access()
+            .reap_exited();
+        let t = CURRENT_SCHEDULER
+            .try_get()
+            .expect("No init")
+            .exclusive_access()
+            .sched_next();
+        // Every thread is waiting, idle until an irq wakes one up
+        let t = match t {
+            Some(t) => t,
+            None => {
+                idle();
+                continue;
+            }
+        };
+        let t_context = t.context.get_raw_addr();
+        switch_to_vmspace(t.get_pagetable());
+        CURRENT_CPU
+            .try_get()
+            .expect("No init")
+            .exclusive_access()
+            .cur_thread = Some(t);
+
+        unsafe {
+            thread_swtch(scheduler_context, t_context);
+        }
+    }
+}
+
+pub fn _yield() {
+    let scheduler_context = CURRENT_SCHEDULER
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .sched_context()
+        .get_raw_addr();
+    let t = CURRENT_CPU
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .reset();
+    let t_context = t.context.get_raw_addr();
+    CURRENT_SCHEDULER
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .push_thread(t);
+    unsafe {
+        thread_swtch(t_context, scheduler_context);
+    }
+}
+
+pub fn _exit(exit_code: i32) -> ! {
+    let scheduler_context = CURRENT_SCHEDULER
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .sched_context()
+        .get_raw_addr();
+    let mut t = CURRENT_CPU
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .take_current();
+    t.state = ThreadState::EXITING;
+    t.exit_code = exit_code;
+    t.release_space();
+    let t_context = t.context.get_raw_addr();
+    CURRENT_SCHEDULER
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .push_thread(t);
+    unsafe {
+        thread_swtch(t_context, scheduler_context);
+    }
+    unreachable!("Exited thread is scheduled again");
+}
+
+// Block the current thread until wakeup(chan) is called.
+// The caller checks its condition again after return.
+pub fn sleep(chan: usize) {
+    let scheduler_context = CURRENT_SCHEDULER
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .sched_context()
+        .get_raw_addr();
+    let mut t = CURRENT_CPU
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .take_current();
+    t.chanel = Some(chan);
+    t.state = ThreadState::WAITING;
+    let t_context = t.context.get_raw_addr();
+    CURRENT_SCHEDULER
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .push_thread(t);
+    unsafe {
+        thread_swtch(t_context, scheduler_context);
+    }
+}
+
+// Sleep on chan until wakeup(chan) or the counter reaches deadline.
+// Return true if the deadline has passed.
+pub fn sleep_timeout(chan: usize, deadline: u64) -> bool {
+    timer::add_timer(deadline, chan);
+    sleep(chan);
+    timer::cancel_timer(chan);
+    timer::counter() >= deadline
+}
+
+pub fn wakeup(chan: usize) {
+    CURRENT_SCHEDULER
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .wakeup(chan);
+}
+
+// Called on a timer tick, the running thread yields
+// once the policy preempts it
+pub fn on_tick() {
+    let preempt = {
+        let mut cpu = CURRENT_CPU.try_get().expect("No init").exclusive_access();
+        // The scheduler may be idle with no thread to yield
+        match cpu.cur_thread.as_mut() {
+            Some(t) => CURRENT_SCHEDULER
+                .try_get()
+                .expect("No init")
+                .exclusive_access()
+                .tick(t),
+            None => false,
+        }
+    };
+    if preempt {
+        _yield();
+    }
+}
+
+// The process running or queued with the pid
+pub fn find_process(pid: usize) -> Option<Arc<UPSafeCell<Process>>> {
+    let running = CURRENT_CPU
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .cur_thread
+        .as_ref()
+        .and_then(|_t| _t.process.clone())
+        .filter(|_p| _p.exclusive_access().pid() == pid);
+    running.or_else(|| {
+        CURRENT_SCHEDULER
+            .try_get()
+            .expect("No init")
+            .exclusive_access()
+            .find_process(pid)
+    })
+}
+
+// Requeue the threads after a nice value changed
+pub fn renice() {
+    CURRENT_SCHEDULER
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .renice();
+}
+
+pub fn kill(pid: usize) -> bool {
+    let running = CURRENT_CPU
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .cur_thread
+        .as_ref()
+        .and_then(|_t| _t.process.clone())
+        .filter(|_p| _p.exclusive_access().pid() == pid);
+    if let Some(process) = running {
+        process.exclusive_access().kill();
+        return true;
+    }
+    CURRENT_SCHEDULER
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .kill(pid)
+}
+
+// Called before returning to user mode
+pub fn exit_if_killed() {
+    let killed = CURRENT_CPU
+        .try_get()
+        .expect("No init")
+        .exclusive_access()
+        .cur_thread
+        .as_ref()
+        .and_then(|_t| _t.process.as_ref().map(|_p| _p.exclusive_access().is_killed()))
+        .unwrap_or(false);
+    if killed {
+        _exit(EXIT_KILLED);
+    }
+}
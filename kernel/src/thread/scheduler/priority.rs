@@ -0,0 +1,61 @@
+use super::Scheduler;
+use crate::thread::Thread;
+use alloc::collections::VecDeque;
+
+const LEVELS: usize = 4;
+// A lower level runs shorter but first
+const TIME_SLICE: [usize; LEVELS] = [1, 1, 2, 4];
+
+// Run the threads of the highest non-empty level in turn.
+// The level comes from the nice value of the process, -20 to 19.
+// There is no aging, a busy high level starves the lower ones.
+#[derive(Default)]
+pub struct PriorityScheduler {
+    levels: [VecDeque<Thread>; LEVELS],
+}
+
+fn level(t: &Thread) -> usize {
+    ((t.nice() + 20) as usize * LEVELS / 40).min(LEVELS - 1)
+}
+
+impl PriorityScheduler {
+    fn first_level(&self) -> Option<usize> {
+        self.levels.iter().position(|_q| !_q.is_empty())
+    }
+}
+
+impl Scheduler for PriorityScheduler {
+    fn enqueue(&mut self, t: Thread) {
+        self.levels[level(&t)].push_back(t);
+    }
+    fn pick_next(&mut self) -> Option<Thread> {
+        let level = self.first_level()?;
+        self.levels[level].pop_front()
+    }
+    // Preempt at the end of the slice or once a higher level is ready,
+    // the rest of the slice is kept in the latter case
+    fn on_tick(&mut self, cur: &mut Thread) -> bool {
+        let level = level(cur);
+        cur.ticks += 1;
+        if cur.ticks >= TIME_SLICE[level] {
+            cur.ticks = 0;
+            return true;
+        }
+        self.first_level().map_or(false, |_l| _l < level)
+    }
+    // Move every queued thread to the level of its nice value
+    fn on_renice(&mut self) {
+        for _l in 0..LEVELS {
+            let queue = core::mem::take(&mut self.levels[_l]);
+            for _t in queue {
+                let level = level(&_t);
+                self.levels[level].push_back(_t);
+            }
+        }
+    }
+    fn for_each(&mut self, f: &mut dyn FnMut(&mut Thread)) {
+        for _q in self.levels.iter_mut() {
+            _q.iter_mut().for_each(&mut *f);
+        }
+    }
+}
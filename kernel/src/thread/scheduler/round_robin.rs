@@ -0,0 +1,32 @@
+use super::Scheduler;
+use crate::thread::Thread;
+use alloc::collections::VecDeque;
+
+// Ticks a thread runs before it is preempted
+const TIME_SLICE: usize = 1;
+
+// Run the threads in turn, every thread gets the same time slice
+#[derive(Default)]
+pub struct RoundRobin {
+    queue: VecDeque<Thread>,
+}
+
+impl Scheduler for RoundRobin {
+    fn enqueue(&mut self, t: Thread) {
+        self.queue.push_back(t);
+    }
+    fn pick_next(&mut self) -> Option<Thread> {
+        self.queue.pop_front()
+    }
+    fn on_tick(&mut self, cur: &mut Thread) -> bool {
+        cur.ticks += 1;
+        if cur.ticks >= TIME_SLICE {
+            cur.ticks = 0;
+            return true;
+        }
+        false
+    }
+    fn for_each(&mut self, f: &mut dyn FnMut(&mut Thread)) {
+        self.queue.iter_mut().for_each(f);
+    }
+}
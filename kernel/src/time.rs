@@ -0,0 +1,40 @@
+use crate::driver::{rtc, timer};
+use conquer_once::spin::OnceCell;
+
+pub const NSEC_PER_SEC: u64 = 1_000_000_000;
+
+// The wall-clock time at boot in nanoseconds since the epoch
+static BOOT_TIME: OnceCell<u64> = OnceCell::uninit();
+
+// Read the rtc once, the wall clock then follows the counter
+pub fn init() {
+    let secs = match rtc::rtc_read() {
+        Some(secs) => secs,
+        None => {
+            warn!("No rtc found, the wall clock starts at the epoch");
+            0
+        }
+    };
+    let boot_time = (secs * NSEC_PER_SEC).saturating_sub(monotonic_ns());
+    BOOT_TIME.try_init_once(|| boot_time).expect("Only init once");
+    info!("Wall clock: {} s since the epoch", secs);
+}
+
+pub fn counter_to_ns(count: u64) -> u64 {
+    (count as u128 * NSEC_PER_SEC as u128 / timer::frequency() as u128) as u64
+}
+
+pub fn ns_to_counter(ns: u64) -> u64 {
+    let count = ns as u128 * timer::frequency() as u128 / NSEC_PER_SEC as u128;
+    count.min(u64::MAX as u128) as u64
+}
+
+// Nanoseconds since boot, it never goes back
+pub fn monotonic_ns() -> u64 {
+    counter_to_ns(timer::counter())
+}
+
+// Nanoseconds since the epoch
+pub fn realtime_ns() -> u64 {
+    BOOT_TIME.try_get().map_or(0, |_b| *_b) + monotonic_ns()
+}
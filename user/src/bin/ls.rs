@@ -0,0 +1,28 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{list_dir, DT_BLK, DT_CHR, DT_DIR};
+
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    let path = if argc > 1 { argv[1] } else { "/" };
+    let entries = match list_dir(path) {
+        Some(entries) => entries,
+        None => {
+            println!("ls: cannot open {}", path);
+            return -1;
+        }
+    };
+    for (name, d_type) in entries.iter() {
+        let suffix = match *d_type {
+            DT_DIR => "/",
+            DT_CHR | DT_BLK => "*",
+            _ => "",
+        };
+        println!("{}{}", name, suffix);
+    }
+    0
+}
@@ -0,0 +1,28 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exec, exit, fork, waitpid};
+
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    println!("argc = {}", argc);
+    for (i, arg) in argv.iter().enumerate() {
+        println!("argv[{}] = {}", i, arg);
+    }
+    if argc == 1 {
+        let pid = fork();
+        if pid == 0 {
+            exec("test_args", &["test_args", "hello", "world"]);
+            exit(-1);
+        }
+        let mut exit_code: i32 = 0;
+        assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+        assert_eq!(exit_code, 0);
+    } else {
+        assert_eq!(argv, &["test_args", "hello", "world"]);
+    }
+    0
+}
@@ -0,0 +1,72 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, open, read, write, O_CREAT, O_RDONLY, O_RDWR, O_TRUNC, O_WRONLY};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // /dev/null takes everything and reads nothing
+    let fd = open("/dev/null", O_RDWR);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    assert_eq!(write(fd, b"discarded"), 9);
+    let mut buf = [1u8; 16];
+    assert_eq!(read(fd, &mut buf), 0);
+    close(fd);
+
+    // /dev/zero fills the buffer
+    let fd = open("/dev/zero", O_RDONLY);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    assert_eq!(read(fd, &mut buf), 16);
+    assert!(buf.iter().all(|_b| *_b == 0));
+    close(fd);
+
+    // A device can not be created
+    assert_eq!(open("/dev/foo", O_CREAT | O_WRONLY), -1);
+    // The raw disk and the stats are read only
+    assert_eq!(open("/dev/cpustat", O_WRONLY), -1);
+    assert_eq!(open("/dev/vda", O_RDWR), -1);
+
+    let fd = open("/dev/console", O_WRONLY);
+    assert!(fd >= 0);
+    assert_eq!(write(fd as usize, b"write to /dev/console\n"), 22);
+    close(fd as usize);
+
+    // A file in /tmp spans several frames
+    let text = b"Hello, tmpfs!\n";
+    let fd = open("/tmp/test_devfs", O_CREAT | O_WRONLY | O_TRUNC);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    for _ in 0..1000 {
+        assert_eq!(write(fd, text), text.len() as isize);
+    }
+    close(fd);
+    let fd = open("/tmp/test_devfs", O_RDONLY);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    let mut total = 0;
+    loop {
+        let len = read(fd, &mut buf[..text.len()]);
+        if len == 0 {
+            break;
+        }
+        assert_eq!(&buf[..len as usize], &text[..len as usize]);
+        total += len;
+    }
+    close(fd);
+    assert_eq!(total, 1000 * text.len() as isize);
+
+    // Truncate on open empties the file
+    let fd = open("/tmp/test_devfs", O_WRONLY | O_TRUNC);
+    assert!(fd >= 0);
+    close(fd as usize);
+    let fd = open("/tmp/test_devfs", O_RDONLY);
+    assert_eq!(read(fd as usize, &mut buf), 0);
+    close(fd as usize);
+    println!("test_devfs OK");
+    0
+}
@@ -0,0 +1,25 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exec, exit, fork, getpid, waitpid};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        println!("child pid {} exec hello_world", getpid());
+        exec("hello_world", &["hello_world"]);
+        println!("exec hello_world failed");
+        exit(-1);
+    }
+    assert!(pid > 0, "fork failed");
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert_eq!(exec("no_such_app", &["no_such_app"]), -1);
+    println!("exec test passed");
+    0
+}
@@ -0,0 +1,38 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, dup, dup2, open, read, write, O_RDWR, O_WRONLY};
+
+const STDOUT: usize = 1;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // The lowest free descriptor is handed out
+    let fd = open("/dev/console", O_WRONLY);
+    assert_eq!(fd, 3);
+    let fd = fd as usize;
+    assert_eq!(write(fd, b"write to /dev/console\n"), 22);
+    let mut buf = [0u8; 1];
+    assert_eq!(read(fd, &mut buf), -1);
+
+    let copy = dup(STDOUT);
+    assert_eq!(copy, 4);
+    assert_eq!(write(copy as usize, b"write to dup(1)\n"), 16);
+
+    // dup2 closes the target first
+    assert_eq!(dup2(fd, copy as usize), copy);
+    assert_eq!(write(copy as usize, b"write to dup2(3, 4)\n"), 20);
+    assert_eq!(dup2(fd, 10), 10);
+    assert_eq!(close(10), 0);
+    assert_eq!(close(10), -1);
+
+    assert_eq!(close(fd), 0);
+    assert_eq!(close(copy as usize), 0);
+    assert_eq!(write(fd, b"closed"), -1);
+    assert_eq!(open("/not_exist", O_RDWR), -1);
+    println!("test_fd OK");
+    0
+}
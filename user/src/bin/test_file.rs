@@ -0,0 +1,39 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, open, read, sync, write, O_CREAT, O_RDONLY, O_TRUNC, O_WRONLY};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let text = b"Hello, easy-fs!\n";
+    let fd = open("/test_file", O_CREAT | O_WRONLY | O_TRUNC);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    // Span several blocks
+    for _ in 0..100 {
+        assert_eq!(write(fd, text), text.len() as isize);
+    }
+    close(fd);
+
+    let fd = open("/test_file", O_RDONLY);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    let mut buf = [0u8; 16];
+    let mut total = 0;
+    loop {
+        let len = read(fd, &mut buf);
+        if len == 0 {
+            break;
+        }
+        assert_eq!(&buf[..len as usize], &text[..len as usize]);
+        total += len;
+    }
+    close(fd);
+    assert_eq!(total, 100 * text.len() as isize);
+    assert_eq!(sync(), 0);
+    println!("test_file OK");
+    0
+}
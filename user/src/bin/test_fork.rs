@@ -0,0 +1,32 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, getpid, waitpid};
+
+static mut COUNTER: usize = 100;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut local: usize = 1;
+    let pid = fork();
+    if pid == 0 {
+        // The writes go to private copies of the shared frames
+        local += 1;
+        unsafe {
+            COUNTER += 1;
+        }
+        println!("child pid {}: local {} counter {}", getpid(), local, unsafe { COUNTER });
+        exit(7);
+    }
+    assert!(pid > 0, "fork failed");
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 7);
+    assert_eq!(local, 1);
+    assert_eq!(unsafe { COUNTER }, 100);
+    println!("parent pid {}: fork test passed", getpid());
+    0
+}
@@ -0,0 +1,29 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+use user_lib::sbrk;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // Much more than the initial 16K of HEAP_SPACE
+    let mut s = String::new();
+    for i in 0..100_000 {
+        s.push((b'a' + (i % 26) as u8) as char);
+    }
+    assert_eq!(s.len(), 100_000);
+    let v: Vec<usize> = (0..200_000).collect();
+    assert_eq!(v.iter().sum::<usize>(), 199_999 * 200_000 / 2);
+
+    let brk = sbrk(0);
+    assert!(brk > 0);
+    assert_eq!(sbrk(4096), brk);
+    unsafe { (brk as *mut u8).write_volatile(1) };
+    assert_eq!(sbrk(-4096), brk + 4096);
+    println!("heap test passed");
+    0
+}
@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::string::String;
+use user_lib::{close, open, read, O_RDONLY};
+
+// Return the value of the line starting with key
+fn stat_value(stat: &str, key: &str) -> u64 {
+    stat.lines()
+        .find_map(|_l| _l.strip_prefix(key))
+        .map(|_v| _v.trim().parse().unwrap())
+        .unwrap()
+}
+
+fn read_stat() -> String {
+    let fd = open("/dev/cpustat", O_RDONLY);
+    assert!(fd >= 0);
+    let mut buf = [0u8; 128];
+    let mut stat = String::new();
+    loop {
+        let len = read(fd as usize, &mut buf);
+        assert!(len >= 0);
+        if len == 0 {
+            break;
+        }
+        stat.push_str(core::str::from_utf8(&buf[..len as usize]).unwrap());
+    }
+    close(fd as usize);
+    stat
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let stat = read_stat();
+    print!("{}", stat);
+    let uptime = stat_value(&stat, "uptime_ms");
+    let idle = stat_value(&stat, "idle_ms");
+    assert!(idle <= uptime);
+    // Both clocks only move forward
+    let stat = read_stat();
+    assert!(stat_value(&stat, "uptime_ms") >= uptime);
+    assert!(stat_value(&stat, "idle_ms") >= idle);
+    println!("test_idle OK");
+    0
+}
@@ -0,0 +1,45 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, exec, fork, list_dir, open, read, waitpid, DT_REG, O_CREAT, O_RDWR, O_RDONLY, O_WRONLY,
+};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // Every app is listed in /bin
+    let entries = list_dir("/bin").unwrap();
+    for app in ["hello_world", "ls", "test_initramfs"] {
+        assert!(entries.iter().any(|(name, d_type)| name == app && *d_type == DT_REG));
+    }
+    // The entries of the root include the mount points
+    let entries = list_dir("/").unwrap();
+    assert!(entries.iter().any(|(name, _)| name == "bin"));
+    assert!(list_dir("/bin/hello_world").is_none());
+
+    let fd = open("/bin/hello_world", O_RDONLY);
+    assert!(fd >= 0);
+    let mut magic = [0u8; 4];
+    assert_eq!(read(fd as usize, &mut magic), 4);
+    assert_eq!(&magic, b"\x7fELF");
+    close(fd as usize);
+
+    // /bin is read only, a write open fails
+    assert_eq!(open("/bin/hello_world", O_WRONLY), -1);
+    assert_eq!(open("/bin/hello_world", O_RDWR), -1);
+    assert_eq!(open("/bin/new_app", O_CREAT | O_WRONLY), -1);
+
+    let pid = fork();
+    if pid == 0 {
+        exec("/bin/hello_world", &["hello_world"]);
+        panic!("exec /bin/hello_world failed");
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    println!("test_initramfs OK");
+    0
+}
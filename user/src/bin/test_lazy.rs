@@ -0,0 +1,25 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+const LEN: usize = 16 * 1024 * 1024;
+// Only the touched pages take physical memory
+static mut SPARSE: [u8; LEN] = [0; LEN];
+
+#[no_mangle]
+pub fn main() -> i32 {
+    unsafe {
+        let mut i = 0;
+        while i < LEN {
+            assert_eq!(SPARSE[i], 0);
+            SPARSE[i] = 1;
+            i += 1024 * 1024;
+        }
+        let sum: usize = (0..LEN).step_by(1024 * 1024).map(|i| SPARSE[i] as usize).sum();
+        assert_eq!(sum, LEN / (1024 * 1024));
+    }
+    println!("lazy bss test passed");
+    0
+}
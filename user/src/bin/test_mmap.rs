@@ -0,0 +1,38 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, mmap, mprotect, munmap, waitpid, PROT_READ, PROT_WRITE};
+
+const LEN: usize = 64 * 1024;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let addr = mmap(0, LEN, PROT_READ | PROT_WRITE);
+    assert!(addr > 0, "mmap failed");
+    let buf = unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, LEN) };
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    assert!(buf.iter().enumerate().all(|(i, b)| *b == i as u8));
+    // A fixed address on an existing mapping fails
+    assert_eq!(mmap(addr as usize, LEN, PROT_READ), -1);
+
+    // Writing a read-only page kills the process
+    assert_eq!(mprotect(addr as usize, 4096, PROT_READ), 0);
+    assert_eq!(buf[1], 1);
+    let pid = fork();
+    if pid == 0 {
+        unsafe { (addr as *mut u8).write_volatile(0) };
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -2);
+
+    assert_eq!(munmap(addr as usize, LEN), 0);
+    println!("mmap test passed");
+    0
+}
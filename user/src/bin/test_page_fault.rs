@@ -0,0 +1,42 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, waitpid};
+
+// Use more than the initial 64K of stack
+fn recurse(depth: usize) -> usize {
+    let buf = [depth as u8; 1024];
+    if depth == 0 {
+        return buf[0] as usize;
+    }
+    recurse(depth - 1) + buf[1023] as usize
+}
+
+fn run_child(f: fn()) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        f();
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    exit_code
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let exit_code = run_child(|| {
+        recurse(256);
+    });
+    assert_eq!(exit_code, 0);
+    println!("stack growth passed");
+    let exit_code = run_child(|| unsafe {
+        (0x10 as *mut u8).write_volatile(1);
+    });
+    assert_eq!(exit_code, -2);
+    println!("illegal access killed the child");
+    0
+}
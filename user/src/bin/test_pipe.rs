@@ -0,0 +1,80 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::{close, dup2, exec, exit, fork, pipe, read, waitpid, write};
+
+const STDOUT: usize = 1;
+// More than the pipe holds, the writer has to block
+const TOTAL: usize = 10000;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut fds = [0i32; 2];
+    assert_eq!(pipe(&mut fds), 0);
+    let (read_end, write_end) = (fds[0] as usize, fds[1] as usize);
+    let pid = fork();
+    if pid == 0 {
+        close(read_end);
+        let data: Vec<u8> = (0..TOTAL).map(|_i| (_i % 251) as u8).collect();
+        assert_eq!(write(write_end, &data), TOTAL as isize);
+        close(write_end);
+        exit(0);
+    }
+    // The reader sees the end once the child closes its write end
+    close(write_end);
+    let mut buf = [0u8; 512];
+    let mut total = 0;
+    loop {
+        let len = read(read_end, &mut buf);
+        assert!(len >= 0);
+        if len == 0 {
+            break;
+        }
+        for _b in buf[..len as usize].iter() {
+            assert_eq!(*_b, (total % 251) as u8);
+            total += 1;
+        }
+    }
+    assert_eq!(total, TOTAL);
+    close(read_end);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    // Without a reader the write fails
+    assert_eq!(pipe(&mut fds), 0);
+    close(fds[0] as usize);
+    assert_eq!(write(fds[1] as usize, b"lost"), -1);
+    close(fds[1] as usize);
+
+    // hello_world | this process
+    assert_eq!(pipe(&mut fds), 0);
+    let pid = fork();
+    if pid == 0 {
+        dup2(fds[1] as usize, STDOUT);
+        close(fds[0] as usize);
+        close(fds[1] as usize);
+        exec("hello_world", &["hello_world"]);
+        exit(-1);
+    }
+    close(fds[1] as usize);
+    let mut output = Vec::new();
+    loop {
+        let len = read(fds[0] as usize, &mut buf);
+        if len <= 0 {
+            break;
+        }
+        output.extend_from_slice(&buf[..len as usize]);
+    }
+    close(fds[0] as usize);
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert!(!output.is_empty());
+    println!("read from hello_world: {}", core::str::from_utf8(&output).unwrap().trim_end());
+    println!("test_pipe OK");
+    0
+}
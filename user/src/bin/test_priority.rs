@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, getpid, setpriority, waitpid, yield_};
+
+const CHILDREN: usize = 3;
+const ROUNDS: usize = 5;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(setpriority(0, -5), 0);
+    assert_eq!(setpriority(getpid() as usize, 0), 0);
+    // The value is clamped
+    assert_eq!(setpriority(0, 100), 0);
+    assert_eq!(setpriority(0, 0), 0);
+    assert_eq!(setpriority(100000, 0), -1);
+    // Only the caller and its children can be changed
+    assert_eq!(setpriority(1, 0), -1);
+
+    // Each child runs at its own nice value, with the priority
+    // scheduler the lower ones finish first
+    let mut pids = [0usize; CHILDREN];
+    for (_i, _pid) in pids.iter_mut().enumerate() {
+        let pid = fork();
+        if pid == 0 {
+            for _round in 0..ROUNDS {
+                println!("pid {} round {}", getpid(), _round);
+                yield_();
+            }
+            exit(_i as i32);
+        }
+        assert!(pid > 0);
+        assert_eq!(setpriority(pid as usize, 10 - 10 * _i as isize), 0);
+        *_pid = pid as usize;
+    }
+    // waitpid yields in a loop, at the lowest priority
+    // the parent does not starve the children
+    assert_eq!(setpriority(0, 19), 0);
+    for (_i, _pid) in pids.iter().enumerate() {
+        let mut exit_code = 0;
+        assert_eq!(waitpid(*_pid, &mut exit_code), *_pid as isize);
+        assert_eq!(exit_code, _i as i32);
+    }
+    println!("test_priority OK");
+    0
+}
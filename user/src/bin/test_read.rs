@@ -5,12 +5,21 @@
 #[macro_use]
 extern crate user_lib;
 
+const STDIN: usize = 0;
 
 #[no_mangle]
 pub fn main() -> i32 {
-    let mut buf:[u8;1];
+    let mut buf = [0u8; 16];
     loop{
-        let ch=user_lib::getchar();
-        print!("{}",ch as char);
+        let len = user_lib::read(STDIN, &mut buf);
+        if len <= 0 {
+            println!("read failed: {}", len);
+            return -1;
+        }
+        println!("read {} bytes", len);
+        if buf[..len as usize].contains(&b'q') {
+            break;
+        }
     }
-}
\ No newline at end of file
+    0
+}
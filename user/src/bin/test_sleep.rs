@@ -0,0 +1,53 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, exit, fork, pipe, read, waitpid, write,
+    time::{nanosleep, sleep_ms, TimeSpec},
+};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let invalid = TimeSpec {
+        sec: 0,
+        nsec: 1_000_000_000,
+    };
+    assert_eq!(nanosleep(&invalid, None), -1);
+    assert_eq!(sleep_ms(0), 0);
+    assert_eq!(sleep_ms(100), 0);
+
+    // The children write their ids in the order they wake up
+    let mut fds = [0i32; 2];
+    assert_eq!(pipe(&mut fds), 0);
+    let delays = [900, 300, 600];
+    let mut pids = [0usize; 3];
+    for (_i, _pid) in pids.iter_mut().enumerate() {
+        let pid = fork();
+        if pid == 0 {
+            close(fds[0] as usize);
+            assert_eq!(sleep_ms(delays[_i]), 0);
+            write(fds[1] as usize, &[_i as u8]);
+            exit(0);
+        }
+        *_pid = pid as usize;
+    }
+    close(fds[1] as usize);
+    let mut order = [0u8; 3];
+    let mut len = 0;
+    while len < order.len() {
+        let l = read(fds[0] as usize, &mut order[len..]);
+        assert!(l > 0);
+        len += l as usize;
+    }
+    close(fds[0] as usize);
+    assert_eq!(order, [1, 2, 0]);
+    for _pid in pids.iter() {
+        let mut exit_code = 0;
+        assert_eq!(waitpid(*_pid, &mut exit_code), *_pid as isize);
+    }
+    println!("test_sleep OK");
+    0
+}
@@ -0,0 +1,19 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{spawn, waitpid};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let pid = spawn("hello_world");
+    assert!(pid > 0, "spawn hello_world failed");
+    let mut exit_code: i32 = 0;
+    let exit_pid = waitpid(pid as usize, &mut exit_code);
+    assert_eq!(pid, exit_pid);
+    println!("hello_world(pid {}) exited with code {}", pid, exit_code);
+    assert_eq!(spawn("no_such_app"), -1);
+    0
+}
@@ -0,0 +1,32 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::time::{clock_gettime, gettimeofday, monotonic, realtime, sleep_ms};
+
+// 2020-01-01 00:00:00 UTC
+const EPOCH_2020: i64 = 1577836800;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert!(clock_gettime(42).is_none());
+
+    // The monotonic clock follows a sleep
+    let start = monotonic();
+    assert_eq!(sleep_ms(200), 0);
+    let elapsed = monotonic().since(&start);
+    assert!(elapsed.as_ms() >= 200, "slept {} ms", elapsed.as_ms());
+    assert!(monotonic() >= start);
+
+    // The wall clock starts from the rtc
+    let now = realtime();
+    println!("realtime: {}.{:09}", now.sec, now.nsec);
+    assert!(now.sec > EPOCH_2020);
+    let tv = gettimeofday().unwrap();
+    assert!(tv.usec >= 0 && tv.usec < 1_000_000);
+    assert!(tv.sec >= now.sec && tv.sec - now.sec <= 1);
+    println!("test_time OK");
+    0
+}
@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{getpid, ioctl, read, ECHO, ICANON, ISIG, TCGETS, TCSETS, TIOCGPGRP, TIOCSPGRP};
+
+const STDIN: usize = 0;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mode = ioctl(STDIN, TCGETS, 0) as usize;
+    assert_eq!(mode, ISIG | ICANON | ECHO);
+    // The tty is handed to this process by its parent,
+    // only a live pid can take it over
+    assert_eq!(ioctl(STDIN, TIOCGPGRP, 0), getpid());
+    assert_eq!(ioctl(STDIN, TIOCSPGRP, 9999), -1);
+    assert_eq!(ioctl(STDIN, TIOCSPGRP, getpid() as usize), 0);
+
+    // Canonical mode hands out a whole line at a time
+    println!("type a line, try backspace, ctrl+u and ctrl+w:");
+    let mut buf = [0u8; 128];
+    let len = read(STDIN, &mut buf);
+    assert!(len > 0 && buf[len as usize - 1] == b'\n');
+    print!("got: {}", core::str::from_utf8(&buf[..len as usize]).unwrap());
+
+    println!("press ctrl+d on an empty line:");
+    assert_eq!(read(STDIN, &mut buf), 0);
+    println!("got EOF");
+
+    // Raw mode hands out every key without echo
+    ioctl(STDIN, TCSETS, ISIG);
+    println!("raw mode, press keys, q to quit:");
+    loop {
+        let len = read(STDIN, &mut buf);
+        for ch in buf[..len as usize].iter() {
+            println!("key {}", ch);
+        }
+        if buf[..len as usize].contains(&b'q') {
+            break;
+        }
+    }
+    ioctl(STDIN, TCSETS, mode);
+    println!("test_tty OK, press ctrl+c to interrupt:");
+    loop {
+        read(STDIN, &mut buf);
+    }
+}
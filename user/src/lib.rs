@@ -7,12 +7,44 @@
 pub mod console;
 mod syscall;
 mod lang_items;
+pub mod time;
 
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
 use buddy_system_allocator::LockedHeap;
+use core::{alloc::{GlobalAlloc, Layout}, ptr::NonNull};
 const USER_HEAP_SIZE: usize = 16384;
+// The heap grows at least this much at a time
+const USER_HEAP_GROW_SIZE: usize = 64 * 1024;
+const PAGE_SIZE: usize = 4096;
 static mut HEAP_SPACE: [u8; USER_HEAP_SIZE] = [0; USER_HEAP_SIZE];
 #[global_allocator]
-static HEAP: LockedHeap = LockedHeap::empty();
+static HEAP: GrowableHeap = GrowableHeap(LockedHeap::empty());
+
+// Start with HEAP_SPACE and take more memory
+// from the program break when it runs out
+struct GrowableHeap(LockedHeap);
+
+unsafe impl GlobalAlloc for GrowableHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut heap = self.0.lock();
+        if let Ok(ptr) = heap.alloc(layout) {
+            return ptr.as_ptr();
+        }
+        let size = (layout.size() + layout.align()).max(USER_HEAP_GROW_SIZE);
+        let size = (size + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+        let start = sbrk(size as isize);
+        if start == -1 {
+            return core::ptr::null_mut();
+        }
+        heap.add_to_heap(start as usize, start as usize + size);
+        heap.alloc(layout).map_or(core::ptr::null_mut(), |ptr| ptr.as_ptr())
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.lock().dealloc(NonNull::new_unchecked(ptr), layout)
+    }
+}
 
 #[alloc_error_handler]
 pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
@@ -22,23 +54,66 @@ pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
 
 #[no_mangle]
 #[link_section = ".text.entry"]
-pub extern "C" fn _start() -> !{
+pub extern "C" fn _start(argc: usize, argv: usize) -> !{
     unsafe {
-        HEAP.lock()
+        HEAP.0.lock()
             .init(HEAP_SPACE.as_ptr() as usize, USER_HEAP_SIZE);
     }
-    exit(main());
+    // argv points to argc pointers of NUL terminated strings
+    let mut v: Vec<&'static str> = Vec::new();
+    for i in 0..argc {
+        let str_start = unsafe {
+            ((argv + i * core::mem::size_of::<usize>()) as *const usize).read_volatile()
+        };
+        let len = (0usize..)
+            .find(|i| unsafe { ((str_start + *i) as *const u8).read_volatile() == 0 })
+            .unwrap();
+        v.push(
+            core::str::from_utf8(unsafe {
+                core::slice::from_raw_parts(str_start as *const u8, len)
+            })
+            .unwrap(),
+        );
+    }
+    exit(main(argc, v.as_slice()));
     panic!("unreachable after sys_exit");
 }
 
 #[linkage="weak"]
 #[no_mangle]
-fn main()->i32{
+fn main(_argc: usize, _argv: &[&str])->i32{
     panic!("Cannot find main!");
 }
 
 use syscall::*;
 
+pub const PROT_NONE: usize = 0;
+pub const PROT_READ: usize = 1;
+pub const PROT_WRITE: usize = 2;
+pub const PROT_EXEC: usize = 4;
+
+pub const O_RDONLY: u32 = 0;
+pub const O_WRONLY: u32 = 1 << 0;
+pub const O_RDWR: u32 = 1 << 1;
+pub const O_CREAT: u32 = 1 << 6;
+pub const O_TRUNC: u32 = 1 << 9;
+
+// d_type of a directory entry
+pub const DT_CHR: u8 = 2;
+pub const DT_DIR: u8 = 4;
+pub const DT_BLK: u8 = 6;
+pub const DT_REG: u8 = 8;
+
+// tty modes
+pub const ISIG: usize = 0x1;
+pub const ICANON: usize = 0x2;
+pub const ECHO: usize = 0x8;
+// ioctl commands, the argument is passed by value
+pub const TCGETS: usize = 0x5401;
+pub const TCSETS: usize = 0x5402;
+pub const TIOCGPGRP: usize = 0x540f;
+pub const TIOCSPGRP: usize = 0x5410;
+
 pub use console::getchar;
 pub fn write(fd: usize, buf: &[u8]) -> isize { sys_write(fd, buf) }
 pub fn read(fd: usize, buf: &mut [u8]) -> isize { sys_read(fd, buf) }
@@ -46,7 +121,18 @@ pub fn exit(exit_code: i32) -> isize { sys_exit(exit_code) }
 pub fn close(fd: usize) -> isize { sys_close(fd) }
 pub fn open(path: &str, flags: u32) -> isize {sys_open(path, flags)}
 pub fn yield_() -> isize { sys_yield() }
+// pid 0 is the caller, a lower nice value in -20..=19 runs first
+pub fn setpriority(pid: usize, nice: isize) -> isize { sys_setpriority(0, pid, nice) }
 pub fn getpid() -> isize { sys_getpid() }
+pub fn wait(exit_code: &mut i32) -> isize {
+    loop {
+        match sys_waitpid(-1, exit_code as *mut _) {
+            -2 => { yield_(); }
+            // -1 or a real pid
+            exit_pid => return exit_pid,
+        }
+    }
+}
 pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
     loop {
         match sys_waitpid(pid as isize, exit_code as *mut _) {
@@ -57,3 +143,58 @@ pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
     }
 }
 pub fn spawn(path:&str)->isize{sys_spawn(path)}
+pub fn fork() -> isize { sys_fork() }
+pub fn exec(path: &str, args: &[&str]) -> isize {
+    // Pass the arguments as (ptr, len) pairs
+    let args: Vec<[usize; 2]> = args.iter().map(|arg| [arg.as_ptr() as usize, arg.len()]).collect();
+    sys_exec(path, &args)
+}
+// addr 0 lets the kernel choose the address
+pub fn mmap(addr: usize, len: usize, prot: usize) -> isize { sys_mmap(addr, len, prot) }
+pub fn munmap(addr: usize, len: usize) -> isize { sys_munmap(addr, len) }
+pub fn mprotect(addr: usize, len: usize, prot: usize) -> isize { sys_mprotect(addr, len, prot) }
+pub fn brk(addr: usize) -> isize { sys_brk(addr) }
+// Return the old program break or -1
+pub fn sbrk(increment: isize) -> isize {
+    let old = sys_brk(0);
+    let new = old + increment;
+    if sys_brk(new as usize) != new {
+        return -1;
+    }
+    old
+}
+pub fn ioctl(fd: usize, cmd: usize, arg: usize) -> isize { sys_ioctl(fd, cmd, arg) }
+pub fn dup(fd: usize) -> isize { sys_dup(fd) }
+pub fn dup2(old_fd: usize, new_fd: usize) -> isize { sys_dup3(old_fd, new_fd, 0) }
+// Write the cached blocks of every filesystem back to the disk
+pub fn sync() -> isize { sys_sync() }
+// fds[0] is the read end and fds[1] the write end
+pub fn pipe(fds: &mut [i32; 2]) -> isize { sys_pipe2(fds, 0) }
+// Fill buf with linux_dirent64 records, return 0 at the end of the directory
+pub fn getdents(fd: usize, buf: &mut [u8]) -> isize { sys_getdents64(fd, buf) }
+// Return the (name, d_type) of every entry in the directory
+pub fn list_dir(path: &str) -> Option<Vec<(String, u8)>> {
+    let fd = open(path, O_RDONLY);
+    if fd < 0 {
+        return None;
+    }
+    let fd = fd as usize;
+    let mut entries = Vec::new();
+    let mut buf = [0u8; 512];
+    loop {
+        let len = getdents(fd, &mut buf);
+        if len <= 0 {
+            close(fd);
+            return if len == 0 { Some(entries) } else { None };
+        }
+        let mut pos = 0;
+        while pos < len as usize {
+            let reclen = u16::from_ne_bytes([buf[pos + 16], buf[pos + 17]]) as usize;
+            let d_type = buf[pos + 18];
+            let name = &buf[pos + 19..pos + reclen];
+            let name_len = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+            entries.push((String::from_utf8_lossy(&name[..name_len]).into_owned(), d_type));
+            pos += reclen;
+        }
+    }
+}
@@ -1,14 +1,31 @@
 use core::arch::asm;
+use super::time::{TimeSpec, TimeVal};
 
+const SYSCALL_DUP: usize = 23;
+const SYSCALL_DUP3: usize = 24;
+const SYSCALL_IOCTL: usize = 29;
 const SYSCALL_READ:usize = 63;
 const SYSCALL_WRITE: usize = 64;
+const SYSCALL_SYNC: usize = 81;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_NANOSLEEP: usize = 101;
+const SYSCALL_CLOCK_GETTIME: usize = 113;
+const SYSCALL_GETTIMEOFDAY: usize = 169;
 const SYSCALL_OPEN: usize = 56;
 const SYSCALL_CLOSE: usize = 57;
+const SYSCALL_PIPE2: usize = 59;
+const SYSCALL_GETDENTS64: usize = 61;
 const SYSCALL_YIELD: usize = 124;
+const SYSCALL_SETPRIORITY: usize = 140;
 const SYSCALL_WAITPID: usize = 260;
 const SYSCALL_GETPID: usize = 172;
 const SYSCALL_SWPAN:usize =170;
+const SYSCALL_FORK: usize = 220;
+const SYSCALL_EXEC: usize = 221;
+const SYSCALL_BRK: usize = 214;
+const SYSCALL_MUNMAP: usize = 215;
+const SYSCALL_MMAP: usize = 222;
+const SYSCALL_MPROTECT: usize = 226;
 
 
 fn syscall(id: usize, args: [usize; 8]) -> isize {
@@ -39,7 +56,7 @@ pub fn sys_exit(exit_code: i32) -> isize {
 }
 
 pub fn sys_open(path: &str, flags: u32) -> isize {
-    syscall(SYSCALL_OPEN, [path.as_ptr() as usize, flags as usize, 0,0,0,0,0,0])
+    syscall(SYSCALL_OPEN, [path.as_ptr() as usize, path.len(), flags as usize,0,0,0,0,0])
 }
 
 pub fn sys_close(fd: usize) -> isize {
@@ -63,5 +80,70 @@ pub fn sys_waitpid(pid: isize, exit_code: *mut i32) -> isize {
 }
 
 pub fn sys_spawn(path:&str) -> isize{
-    syscall(SYSCALL_SWPAN, [path.as_ptr() as usize,0, 0, 0,0,0,0,0])
-}
\ No newline at end of file
+    syscall(SYSCALL_SWPAN, [path.as_ptr() as usize,path.len(), 0, 0,0,0,0,0])
+}
+
+pub fn sys_fork() -> isize {
+    syscall(SYSCALL_FORK, [0, 0, 0,0,0,0,0,0])
+}
+
+pub fn sys_exec(path: &str, args: &[[usize; 2]]) -> isize {
+    syscall(SYSCALL_EXEC, [path.as_ptr() as usize, path.len(), args.as_ptr() as usize, args.len(),0,0,0,0])
+}
+
+pub fn sys_mmap(addr: usize, len: usize, prot: usize) -> isize {
+    syscall(SYSCALL_MMAP, [addr, len, prot,0,0,0,0,0])
+}
+
+pub fn sys_munmap(addr: usize, len: usize) -> isize {
+    syscall(SYSCALL_MUNMAP, [addr, len, 0,0,0,0,0,0])
+}
+
+pub fn sys_mprotect(addr: usize, len: usize, prot: usize) -> isize {
+    syscall(SYSCALL_MPROTECT, [addr, len, prot,0,0,0,0,0])
+}
+
+pub fn sys_brk(addr: usize) -> isize {
+    syscall(SYSCALL_BRK, [addr, 0, 0,0,0,0,0,0])
+}
+
+pub fn sys_ioctl(fd: usize, cmd: usize, arg: usize) -> isize {
+    syscall(SYSCALL_IOCTL, [fd, cmd, arg,0,0,0,0,0])
+}
+
+pub fn sys_dup(fd: usize) -> isize {
+    syscall(SYSCALL_DUP, [fd, 0, 0,0,0,0,0,0])
+}
+
+pub fn sys_dup3(old_fd: usize, new_fd: usize, flags: usize) -> isize {
+    syscall(SYSCALL_DUP3, [old_fd, new_fd, flags,0,0,0,0,0])
+}
+
+pub fn sys_sync() -> isize {
+    syscall(SYSCALL_SYNC, [0, 0, 0,0,0,0,0,0])
+}
+
+pub fn sys_getdents64(fd: usize, buffer: &mut [u8]) -> isize {
+    syscall(SYSCALL_GETDENTS64, [fd, buffer.as_mut_ptr() as usize, buffer.len(),0,0,0,0,0])
+}
+
+pub fn sys_pipe2(fds: &mut [i32; 2], flags: usize) -> isize {
+    syscall(SYSCALL_PIPE2, [fds.as_mut_ptr() as usize, flags, 0,0,0,0,0,0])
+}
+
+pub fn sys_setpriority(which: usize, who: usize, nice: isize) -> isize {
+    syscall(SYSCALL_SETPRIORITY, [which, who, nice as usize,0,0,0,0,0])
+}
+
+pub fn sys_nanosleep(req: *const TimeSpec, rem: *mut TimeSpec) -> isize {
+    syscall(SYSCALL_NANOSLEEP, [req as usize, rem as usize, 0,0,0,0,0,0])
+}
+
+pub fn sys_clock_gettime(clock_id: usize, tp: *mut TimeSpec) -> isize {
+    syscall(SYSCALL_CLOCK_GETTIME, [clock_id, tp as usize, 0,0,0,0,0,0])
+}
+
+// The timezone is not supported
+pub fn sys_gettimeofday(tv: *mut TimeVal) -> isize {
+    syscall(SYSCALL_GETTIMEOFDAY, [tv as usize, 0, 0,0,0,0,0,0])
+}
@@ -0,0 +1,84 @@
+use super::syscall::*;
+
+const NSEC_PER_SEC: i64 = 1_000_000_000;
+
+// The clocks of clock_gettime
+pub const CLOCK_REALTIME: usize = 0;
+pub const CLOCK_MONOTONIC: usize = 1;
+
+// The struct timespec of the kernel
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TimeSpec {
+    pub sec: i64,
+    pub nsec: i64,
+}
+
+impl TimeSpec {
+    pub fn from_ms(ms: usize) -> Self {
+        Self {
+            sec: (ms / 1000) as i64,
+            nsec: (ms % 1000) as i64 * (NSEC_PER_SEC / 1000),
+        }
+    }
+    pub fn as_ms(&self) -> i64 {
+        self.sec * 1000 + self.nsec / (NSEC_PER_SEC / 1000)
+    }
+    // The time from earlier to self, zero if earlier is later
+    pub fn since(&self, earlier: &TimeSpec) -> TimeSpec {
+        if self <= earlier {
+            return TimeSpec::default();
+        }
+        let mut sec = self.sec - earlier.sec;
+        let mut nsec = self.nsec - earlier.nsec;
+        if nsec < 0 {
+            sec -= 1;
+            nsec += NSEC_PER_SEC;
+        }
+        TimeSpec { sec, nsec }
+    }
+}
+
+// The struct timeval of the kernel
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TimeVal {
+    pub sec: i64,
+    pub usec: i64,
+}
+
+pub fn clock_gettime(clock_id: usize) -> Option<TimeSpec> {
+    let mut tp = TimeSpec::default();
+    match sys_clock_gettime(clock_id, &mut tp as *mut TimeSpec) {
+        0 => Some(tp),
+        _ => None,
+    }
+}
+
+// Time since boot
+pub fn monotonic() -> TimeSpec {
+    clock_gettime(CLOCK_MONOTONIC).unwrap()
+}
+
+// Time since the epoch, read from the rtc at boot
+pub fn realtime() -> TimeSpec {
+    clock_gettime(CLOCK_REALTIME).unwrap()
+}
+
+pub fn gettimeofday() -> Option<TimeVal> {
+    let mut tv = TimeVal::default();
+    match sys_gettimeofday(&mut tv as *mut TimeVal) {
+        0 => Some(tv),
+        _ => None,
+    }
+}
+
+// Return -1 if interrupted, the time left is put in rem
+pub fn nanosleep(req: &TimeSpec, rem: Option<&mut TimeSpec>) -> isize {
+    let rem = rem.map_or(core::ptr::null_mut(), |_r| _r as *mut TimeSpec);
+    sys_nanosleep(req as *const TimeSpec, rem)
+}
+
+pub fn sleep_ms(ms: usize) -> isize {
+    nanosleep(&TimeSpec::from_ms(ms), None)
+}